@@ -1,13 +1,42 @@
+use clap::Parser;
+use std::path::PathBuf;
+
 use bevy::{
     app::ScheduleRunnerPlugin,
     log::{Level, LogPlugin},
     prelude::*,
 };
 
-use server::ServerPlugin;
+use server::{ServerConfig, ServerConfigOverrides, ServerPlugin};
 use wordfight::{ActiveGamePlugin, WordFightPlugins};
 
+/// Runs the wordfight server. Settings come from the config file, then environment variables,
+/// then these flags, with later ones taking precedence.
+#[derive(Debug)]
+#[derive(Parser)]
+struct Args {
+    /// TOML file to read settings from [env: SERVER_CONFIG]
+    #[arg(long)]
+    config: Option<PathBuf>,
+    #[command(flatten)]
+    overrides: ServerConfigOverrides,
+}
+
 fn main() {
+    let args = Args::parse();
+    let config_path = args
+        .config
+        .or_else(|| std::env::var_os("SERVER_CONFIG").map(PathBuf::from));
+    let config = ServerConfigOverrides::from_env(std::env::vars())
+        .and_then(|env| ServerConfig::load(config_path.as_deref(), env, args.overrides));
+    let server = match config.and_then(ServerPlugin::new) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("Failed to start the server: {error}");
+            std::process::exit(1);
+        }
+    };
+
     App::default()
         .add_plugins((
             ScheduleRunnerPlugin::run_loop(
@@ -23,17 +52,6 @@ fn main() {
                 })
                 .disable::<ActiveGamePlugin>(),
         ))
-        .add_plugins(ServerPlugin {
-            port: option_env!("SERVER_PORT").unwrap_or("7636").to_string(),
-            wt_tokens_port: option_env!("SERVER_TOKENS_PORT")
-                .unwrap_or("7637")
-                .to_string(),
-            // native_host: option_env!("SERVER_IP")
-            //     .unwrap_or("127.0.0.1")
-            //     .to_string(),
-            // native_port: option_env!("SERVER_IP")
-            //     .unwrap_or("127.0.0.1")
-            //     .to_string(),
-        })
+        .add_plugins(server)
         .run();
 }
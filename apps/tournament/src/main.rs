@@ -0,0 +1,246 @@
+use clap::Parser;
+use rand_core::SeedableRng;
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_prng::WyRand;
+use bevy_rand::prelude::GlobalEntropy;
+
+use bot_controller::{BotController, BotControllerPlugin, BotDifficulty, BotStrategies};
+use game::{
+    GameLog, GameResult, GameSettings, InGame, PlayerSide, Rating, Score, SpawnGame, WinCondition,
+    WordFightGamePlugin,
+};
+
+// how much game time passes every frame, as if running at about 60fps
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// Plays registered bot strategies against each other in headless games and reports how they fare.
+#[derive(Debug)]
+#[derive(Parser)]
+struct Args {
+    /// Games to play for every pairing of strategies (on each side) and arena size
+    #[arg(long, default_value_t = 10)]
+    games: u64,
+    /// Seeds the first game; every game after it uses the next seed
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    #[arg(long, value_delimiter = ',', default_values_t = [6, 7, 8])]
+    arena_sizes: Vec<usize>,
+    /// Strategies to enter, by name [default: every registered strategy]
+    #[arg(long, value_delimiter = ',')]
+    strategies: Vec<String>,
+    /// How quickly and carefully every bot plays (easy, medium or hard)
+    #[arg(long, default_value = "hard", value_parser = parse_difficulty)]
+    difficulty: BotDifficulty,
+    /// Points needed to win a game
+    #[arg(long, default_value_t = 5)]
+    points: usize,
+    /// Frames after which a game is called a draw
+    #[arg(long, default_value_t = 20_000)]
+    max_frames: u32,
+}
+
+fn parse_difficulty(name: &str) -> Result<BotDifficulty, String> {
+    BotDifficulty::ALL
+        .into_iter()
+        .find(|difficulty| difficulty.to_string().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("unknown difficulty {name}"))
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, WordFightGamePlugin, BotControllerPlugin));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME));
+    app.update();
+
+    let mut strategies = args.strategies.clone();
+    let registered = app.world().resource::<BotStrategies>();
+    if strategies.is_empty() {
+        strategies = registered.names().map(str::to_string).collect();
+        strategies.sort();
+    }
+    if let Some(unknown) = strategies.iter().find(|name| !registered.contains(name)) {
+        eprintln!("Unknown strategy {unknown}");
+        std::process::exit(1);
+    }
+    if strategies.len() < 2 {
+        eprintln!("A tournament needs at least two strategies");
+        std::process::exit(1);
+    }
+
+    let mut overall = Standings::default();
+    let mut by_arena_size = BTreeMap::<usize, Standings>::new();
+    let mut seed = args.seed;
+    for &arena_size in &args.arena_sizes {
+        for left in &strategies {
+            for right in strategies.iter().filter(|right| *right != left) {
+                for _ in 0..args.games {
+                    let (result, strikes) =
+                        play_game(&mut app, &args, seed, arena_size, left, right);
+                    seed += 1;
+                    overall.record(left, right, &result, strikes);
+                    by_arena_size
+                        .entry(arena_size)
+                        .or_default()
+                        .record(left, right, &result, strikes);
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} games of first to {} between {} bots (seeds {}..{})",
+        seed - args.seed,
+        args.points,
+        args.difficulty,
+        args.seed,
+        seed
+    );
+    for (arena_size, standings) in &by_arena_size {
+        println!("\nArena size {arena_size}");
+        standings.print();
+    }
+    println!("\nOverall");
+    overall.print();
+}
+
+// Plays out one game between two bots, returning its result and how many strikes it took.
+// Games that run out of frames are draws at whatever the score was.
+fn play_game(
+    app: &mut App,
+    args: &Args,
+    seed: u64,
+    arena_size: usize,
+    left: &str,
+    right: &str,
+) -> (GameResult, usize) {
+    // bots fork their rngs from the global entropy as they spawn, so reseeding it first
+    // makes each game reproducible by itself
+    app.insert_resource(GlobalEntropy::<WyRand>::from_seed(seed.to_le_bytes()));
+    let mut spawn_bot = |strategy: &str| {
+        app.world_mut()
+            .spawn(
+                BotController::new(args.difficulty)
+                    .with_strategy(strategy)
+                    .bundle(),
+            )
+            .id()
+    };
+    let (left, right) = (spawn_bot(left), spawn_bot(right));
+    app.world_mut()
+        .trigger(SpawnGame::new(arena_size, left, right).with_settings(
+            GameSettings::default().with_win_condition(WinCondition::Points(args.points)),
+        ));
+    app.update();
+    let game = **app
+        .world()
+        .get::<InGame>(left)
+        .expect("tournament game to be spawned");
+
+    for _ in 0..args.max_frames {
+        if app.world().get::<GameResult>(game).is_some() {
+            break;
+        }
+        app.update();
+    }
+    let world = app.world();
+    let result = world
+        .get::<GameResult>(game)
+        .copied()
+        .unwrap_or_else(|| GameResult {
+            winner: None,
+            left_score: **world.get::<Score>(left).unwrap(),
+            right_score: **world.get::<Score>(right).unwrap(),
+            aborted: false,
+            forfeit: false,
+        });
+    let strikes = world
+        .get::<GameLog>(game)
+        .map(|log| log.strikes().count())
+        .unwrap_or_default();
+
+    // the game is cleaned up once it has no players left
+    app.world_mut().despawn(left);
+    app.world_mut().despawn(right);
+    app.update();
+    (result, strikes)
+}
+
+#[derive(Debug, Default)]
+struct Standing {
+    wins: usize,
+    draws: usize,
+    losses: usize,
+    points: usize,
+    strikes: usize,
+    rating: Rating,
+}
+
+impl Standing {
+    fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    fn record(&mut self, points: usize, strikes: usize, winner: Option<bool>) {
+        match winner {
+            Some(true) => self.wins += 1,
+            Some(false) => self.losses += 1,
+            None => self.draws += 1,
+        }
+        self.points += points;
+        self.strikes += strikes;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Standings(BTreeMap<String, Standing>);
+
+impl Standings {
+    fn record(&mut self, left: &str, right: &str, result: &GameResult, strikes: usize) {
+        let winner = |side: PlayerSide| result.winner.map(|winner| winner == side);
+        let mut left_rating = self.standing(left).rating;
+        let mut right_rating = self.standing(right).rating;
+        Rating::update_game(&mut left_rating, &mut right_rating, result);
+
+        let standing = self.standing(left);
+        standing.record(result.left_score, strikes, winner(PlayerSide::Left));
+        standing.rating = left_rating;
+        let standing = self.standing(right);
+        standing.record(result.right_score, strikes, winner(PlayerSide::Right));
+        standing.rating = right_rating;
+    }
+
+    fn standing(&mut self, strategy: &str) -> &mut Standing {
+        self.0.entry(strategy.to_string()).or_default()
+    }
+
+    // best rated first
+    fn print(&self) {
+        println!(
+            "{:<16} {:>6} {:>5} {:>5} {:>6} {:>9} {:>14} {:>6}",
+            "Strategy", "Games", "Wins", "Draws", "Losses", "Win rate", "Points/strike", "Elo"
+        );
+        let mut standings = self.0.iter().collect::<Vec<_>>();
+        standings.sort_by(|(_, standing1), (_, standing2)| {
+            standing2.rating.total_cmp(&standing1.rating)
+        });
+        for (strategy, standing) in standings {
+            let games = standing.games().max(1);
+            let strikes = standing.strikes.max(1);
+            println!(
+                "{:<16} {:>6} {:>5} {:>5} {:>6} {:>8.1}% {:>14.2} {:>6}",
+                strategy,
+                standing.games(),
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                100. * standing.wins as f64 / games as f64,
+                standing.points as f64 / strikes as f64,
+                standing.rating,
+            );
+        }
+    }
+}
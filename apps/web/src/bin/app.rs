@@ -4,9 +4,15 @@ use leptos::*;
 #[cfg(feature = "log")]
 use wasm_bindgen::prelude::*;
 
-use wordfight::PlayerSide;
+use wordfight::{
+    GameResult, PlayerSide, RoomCode, RoomRequest, RoomResponse, Strike, StrikeRule,
+    ValidationMode, METER_CAPACITY, ROOM_CODE_LENGTH,
+};
 use wordfight_web::{AppMessage, BevyWorker};
 
+// where the account key the server hands out is remembered between visits
+const ACCOUNT_KEY_STORAGE_KEY: &str = "wordfight-account-key";
+
 #[cfg(feature = "log")]
 #[wasm_bindgen]
 extern "C" {
@@ -33,7 +39,21 @@ fn App() -> impl IntoView {
     let (left_score, set_left_score) = create_signal(0);
     let (right_word, set_right_word) = create_signal("".to_string());
     let (right_score, set_right_score) = create_signal(0);
+    let (left_meter, set_left_meter) = create_signal(0);
+    let (right_meter, set_right_meter) = create_signal(0);
+    let (left_is_bot, set_left_is_bot) = create_signal(false);
+    let (right_is_bot, set_right_is_bot) = create_signal(false);
     let (arena_size, set_arena_size) = create_signal(7);
+    let (strike_rule, set_strike_rule) = create_signal(StrikeRule::default());
+    let (validation_mode, set_validation_mode) = create_signal(ValidationMode::default());
+    let (result, set_result) = create_signal(None::<GameResult>);
+    let (last_strike, set_last_strike) = create_signal(None::<Strike>);
+    let (shake, set_shake) = create_signal(false);
+    let (room_code, set_room_code) = create_signal(None::<RoomCode>);
+    let (room_message, set_room_message) = create_signal(None::<String>);
+    let (player_name, set_player_name) = create_signal(None::<String>);
+    let (shutdown_message, set_shutdown_message) = create_signal(None::<String>);
+    let (connection_error, set_connection_error) = create_signal(None::<String>);
     #[cfg(feature = "log")]
     log("Render (App)".to_string());
 
@@ -53,16 +73,78 @@ fn App() -> impl IntoView {
                     set_left_score.set(state.left_score);
                     set_right_word.set(state.right_word);
                     set_right_score.set(state.right_score);
+                    set_left_meter.set(state.left_meter);
+                    set_right_meter.set(state.right_meter);
+                    set_left_is_bot.set(state.left_is_bot);
+                    set_right_is_bot.set(state.right_is_bot);
                     set_arena_size.set(state.arena_size);
+                    set_strike_rule.set(state.strike_rule);
+                    set_validation_mode.set(state.validation_mode);
+                    set_result.set(state.result);
+                    set_last_strike.set(state.last_strike);
+                }
+                wordfight_web::WorkerMessage::ActionFeedback(outcome) => {
+                    if !outcome.is_accepted() {
+                        set_shake.set(true);
+                        set_timeout(
+                            move || set_shake.set(false),
+                            std::time::Duration::from_millis(300),
+                        );
+                    }
+                }
+                wordfight_web::WorkerMessage::Room(response) => match response {
+                    RoomResponse::Created(code) | RoomResponse::Joined(code) => {
+                        set_room_code.set(Some(code));
+                        set_room_message.set(None);
+                    }
+                    RoomResponse::Left => {
+                        set_room_code.set(None);
+                        set_room_message.set(None);
+                    }
+                    RoomResponse::NotFound(code) => {
+                        set_room_message.set(Some(format!("There is no room {code}")));
+                    }
+                    RoomResponse::Full(code) => {
+                        set_room_message.set(Some(format!("Room {code} is already full")));
+                    }
+                    RoomResponse::InGame => {
+                        set_room_message
+                            .set(Some("Finish your game before changing rooms".to_string()));
+                    }
+                },
+                wordfight_web::WorkerMessage::Welcome(welcome) => {
+                    if let Ok(Some(storage)) = window().local_storage() {
+                        let _ = storage.set_item(ACCOUNT_KEY_STORAGE_KEY, &welcome.key);
+                    }
+                    set_player_name.set(Some(format!("{} ({})", welcome.name, welcome.rating)));
+                }
+                wordfight_web::WorkerMessage::ServerShutdown(shutdown) => {
+                    set_shutdown_message.set(Some(format!(
+                        "The server is restarting. Games in progress have {}s to finish.",
+                        shutdown.deadline_secs
+                    )));
+                }
+                wordfight_web::WorkerMessage::ConnectionFailed(error) => {
+                    set_connection_error.set(Some(error.to_string()));
                 }
             }
         })
         .spawn("./worker.js");
-    let bridge = Box::leak(Box::new(bridge));
+    let bridge: &'static _ = Box::leak(Box::new(bridge));
+    let account_key = window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| storage.get_item(ACCOUNT_KEY_STORAGE_KEY).ok().flatten());
+    bridge.send(AppMessage::Connect(account_key));
+    let send_room_request =
+        Callback::new(move |request: RoomRequest| bridge.send(AppMessage::Room(request)));
 
     let handle_input = move |event: KeyboardEvent| {
         if let Some(message) = match event.key().as_str() {
             "Backspace" | "Delete" | "ArrowLeft" => Some(AppMessage::Backspace),
+            "1" => Some(AppMessage::SuperCollapse),
+            "2" => Some(AppMessage::SuperExtend),
             letter => AppMessage::add_letter(letter),
         } {
             bridge.send(message);
@@ -83,6 +165,13 @@ fn App() -> impl IntoView {
             right_score.get()
         }
     };
+    let my_meter = move || {
+        if my_side.get() == PlayerSide::Left {
+            left_meter.get()
+        } else {
+            right_meter.get()
+        }
+    };
     let enemy_word = move || {
         if my_side.get() == PlayerSide::Left {
             right_word.get()
@@ -90,6 +179,35 @@ fn App() -> impl IntoView {
             left_word.get()
         }
     };
+    let enemy_is_bot = move || {
+        if my_side.get() == PlayerSide::Left {
+            right_is_bot.get()
+        } else {
+            left_is_bot.get()
+        }
+    };
+    let result_message = move || {
+        result.get().map(|result| match result.winner {
+            _ if result.aborted => "The game was called off.",
+            Some(winner) if result.forfeit && winner == my_side.get() => {
+                "Your opponent left. You win!"
+            }
+            Some(winner) if winner == my_side.get() => "You win!",
+            Some(_) => "You lose!",
+            None => "Draw!",
+        })
+    };
+    let bonus_message = move || match last_strike.get() {
+        Some(Strike::Score(winner, Some(bonus))) => {
+            let who = if winner == my_side.get() {
+                "You"
+            } else {
+                "Enemy"
+            };
+            Some(format!("{who}: {bonus}"))
+        }
+        _ => None,
+    };
     let enemy_score = move || {
         if my_side.get() == PlayerSide::Left {
             right_score.get()
@@ -97,20 +215,102 @@ fn App() -> impl IntoView {
             left_score.get()
         }
     };
+    let waiting_message = move || {
+        if let Some(error) = connection_error.get() {
+            error
+        } else if room_code.get().is_some() {
+            "Waiting for your friend to join...".to_string()
+        } else {
+            "Finding match...".to_string()
+        }
+    };
 
     view! {
         <div class="center" tabindex="1" on:keyup=handle_input>
+            <Show when=move || player_name.get().is_some()>
+                <div class="account">"Playing as " {player_name}</div>
+            </Show>
+            <Show when=move || shutdown_message.get().is_some()>
+                <div class="shutdown">{shutdown_message}</div>
+            </Show>
+            <Room room_code=room_code room_message=room_message on_request=send_room_request />
             <Show
                 when=move || game_started.get()
-                fallback=|| view! { <div>"Finding match..."</div> }
+                fallback=move || view! { <div>{waiting_message}</div> }
             >
+                <Show when=enemy_is_bot>
+                    <div class="opponent">"No one else was around, so you're playing a bot."</div>
+                </Show>
                 <Game
                     my_word=my_word
                     my_score=my_score
+                    my_meter=my_meter
                     enemy_word=enemy_word
                     enemy_score=enemy_score
                     arena_size=arena_size
+                    strike_rule=strike_rule
+                    validation_mode=validation_mode
+                    shake=shake
                 />
+                <Show when=move || bonus_message().is_some()>
+                    <div class="bonus">{bonus_message}</div>
+                </Show>
+                <Show when=move || result_message().is_some()>
+                    <div class="result">{result_message}</div>
+                </Show>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+fn Room(
+    room_code: ReadSignal<Option<RoomCode>>,
+    room_message: ReadSignal<Option<String>>,
+    #[prop(into)] on_request: Callback<RoomRequest>,
+) -> impl IntoView {
+    #[cfg(feature = "log")]
+    log("Render (Room)".to_string());
+    let (code_input, set_code_input) = create_signal(String::new());
+    let (invalid_code, set_invalid_code) = create_signal(None::<String>);
+    let join = move |_| match code_input.get().parse::<RoomCode>() {
+        Ok(code) => {
+            set_invalid_code.set(None);
+            on_request.call(RoomRequest::Join(code));
+        }
+        Err(error) => set_invalid_code.set(Some(error.to_string())),
+    };
+    let message = move || invalid_code.get().or_else(|| room_message.get());
+    view! {
+        <div class="room">
+            <Show
+                when=move || room_code.get().is_some()
+                fallback=move || view! {
+                    <button on:click=move |_| on_request.call(RoomRequest::Create)>
+                        "Create a private room"
+                    </button>
+                    <input
+                        type="text"
+                        placeholder="Room code"
+                        maxlength=ROOM_CODE_LENGTH
+                        prop:value=code_input
+                        on:input=move |event| {
+                            set_invalid_code.set(None);
+                            set_code_input.set(event_target_value(&event));
+                        }
+                        // typing a code should not play letters in the game
+                        on:keyup=|event: KeyboardEvent| event.stop_propagation()
+                    />
+                    <button on:click=join>"Join"</button>
+                }
+            >
+                "Room "
+                {move || room_code.get().map(|code| code.to_string())}
+                " (share this code with a friend to play them) "
+                <button on:click=move |_| on_request.call(RoomRequest::Leave)>"Leave"</button>
+            </Show>
+            <Show when=move || message().is_some()>
+                <div class="room-message">{message}</div>
             </Show>
         </div>
     }
@@ -120,9 +320,13 @@ fn App() -> impl IntoView {
 fn Game(
     #[prop(into)] my_word: Signal<String>,
     #[prop(into)] my_score: Signal<usize>,
+    #[prop(into)] my_meter: Signal<usize>,
     #[prop(into)] enemy_word: Signal<String>,
     #[prop(into)] enemy_score: Signal<usize>,
     arena_size: ReadSignal<usize>,
+    strike_rule: ReadSignal<StrikeRule>,
+    validation_mode: ReadSignal<ValidationMode>,
+    shake: ReadSignal<bool>,
 ) -> impl IntoView {
     #[cfg(feature = "log")]
     log("Render (Game)".to_string());
@@ -131,9 +335,24 @@ fn Game(
             "Arena: "
             {arena_size}
         </div>
+        <div>
+            "Rule: "
+            {move || strike_rule.get().to_string()}
+        </div>
+        <div>
+            "Mode: "
+            {move || validation_mode.get().to_string()}
+        </div>
         <Scoreboard my_score=my_score enemy_score=enemy_score />
+        <div class="meter">
+            "Meter: "
+            {my_meter}
+            "/"
+            {METER_CAPACITY}
+            " (1: Collapse, 2: Extend)"
+        </div>
         <div class="arena">
-            <div class="friendly">
+            <div class="friendly" class:shake=shake>
                 <Word top_word=my_word bottom_word=enemy_word arena_size=arena_size />
             </div>
             <div class="enemy">
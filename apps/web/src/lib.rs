@@ -20,8 +20,13 @@ pub const SERVER_DEFAULT_TOKENS_PORT: &str = "7637";
 #[derive(Debug)]
 #[derive(Deserialize, Serialize)]
 pub enum AppMessage {
+    // sent once on startup, with the account key saved from an earlier visit (if any)
+    Connect(Option<String>),
     AddLetter(Letter),
     Backspace,
+    SuperCollapse,
+    SuperExtend,
+    Room(RoomRequest),
 }
 
 impl AppMessage {
@@ -34,6 +39,36 @@ impl AppMessage {
 #[derive(Deserialize, Serialize)]
 pub enum WorkerMessage {
     UpdateState(UpdateStateMessage),
+    ActionFeedback(ActionOutcome),
+    Room(RoomResponse),
+    Welcome(Welcome),
+    ServerShutdown(ServerShutdown),
+    ConnectionFailed(ConnectionError),
+}
+
+// Why the worker could not connect to the server.
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+pub enum ConnectionError {
+    // the account key we signed in with has been banned
+    Banned,
+    // the server is shutting down, and not letting anyone new in
+    ServerRestarting,
+    Failed(String),
+}
+
+impl std::fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionError::Banned => write!(f, "This account has been banned."),
+            ConnectionError::ServerRestarting => {
+                write!(f, "The server is restarting. Try again in a few minutes.")
+            }
+            ConnectionError::Failed(reason) => {
+                write!(f, "Could not connect to the server: {reason}")
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -42,7 +77,15 @@ pub struct UpdateStateMessage {
     pub my_side: PlayerSide,
     pub left_word: String,
     pub left_score: usize,
+    pub left_meter: usize,
+    pub left_is_bot: bool,
     pub right_word: String,
     pub right_score: usize,
+    pub right_meter: usize,
+    pub right_is_bot: bool,
     pub arena_size: usize,
+    pub strike_rule: StrikeRule,
+    pub validation_mode: ValidationMode,
+    pub last_strike: Option<Strike>,
+    pub result: Option<GameResult>,
 }
@@ -4,18 +4,21 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{Request, RequestInit, RequestMode, Response};
 
-use bevy::{prelude::*, utils::HashSet};
+use bevy::{ecs::event::ManualEventReader, prelude::*, utils::HashSet};
 
 use client::{
     bevy_replicon::prelude::{RepliconClient, RepliconClientStatus},
-    ClientPlugin,
+    ClientError, ClientPlugin,
+};
+use wordfight::{
+    ActionEvent, ActionFeedback, ActiveGameUpdate, Client, PlayerSide, RoomResponse,
+    ServerShutdown, Welcome, WordFightPlugins,
 };
-use wordfight::{ActiveGameUpdate, Client, PlayerSide, WordFightPlugins};
 
 use crate::{
-    AppMessage, UpdateStateMessage, WorkerMessage, SERVER_DEFAULT_IP, SERVER_DEFAULT_ORIGIN,
-    SERVER_DEFAULT_PORT, SERVER_DEFAULT_TOKENS_PORT, SERVER_IP, SERVER_ORIGIN, SERVER_PORT,
-    SERVER_TOKENS_PORT,
+    AppMessage, ConnectionError, UpdateStateMessage, WorkerMessage, SERVER_DEFAULT_IP,
+    SERVER_DEFAULT_ORIGIN, SERVER_DEFAULT_PORT, SERVER_DEFAULT_TOKENS_PORT, SERVER_IP,
+    SERVER_ORIGIN, SERVER_PORT, SERVER_TOKENS_PORT,
 };
 
 // Use this to enable console logging
@@ -33,6 +36,11 @@ extern "C" {
 pub struct BevyWorker {
     game: Option<App>,
     subscriptions: HashSet<HandlerId>,
+    // kept across updates so that each feedback event is only forwarded once
+    feedback_reader: ManualEventReader<ActionFeedback>,
+    room_reader: ManualEventReader<RoomResponse>,
+    welcome_reader: ManualEventReader<Welcome>,
+    shutdown_reader: ManualEventReader<ServerShutdown>,
     _trigger_update: Closure<dyn FnMut()>,
     _interval: Interval,
 }
@@ -43,8 +51,7 @@ impl Worker for BevyWorker {
     type Message = WorkerUpdateMessage;
 
     fn create(scope: &WorkerScope<Self>) -> Self {
-        scope
-            .send_future(async { WorkerUpdateMessage::Token(fetch_server_token().await.unwrap()) });
+        // the token is fetched once the app sends its account key with `AppMessage::Connect`
         let scope_clone = scope.clone();
         let trigger_update = Closure::new(move || {
             scope_clone.send_message(WorkerUpdateMessage::Update);
@@ -53,6 +60,10 @@ impl Worker for BevyWorker {
         Self {
             game: None,
             subscriptions: HashSet::default(),
+            feedback_reader: ManualEventReader::default(),
+            room_reader: ManualEventReader::default(),
+            welcome_reader: ManualEventReader::default(),
+            shutdown_reader: ManualEventReader::default(),
             _trigger_update: trigger_update,
             _interval: Interval(interval),
         }
@@ -69,6 +80,31 @@ impl Worker for BevyWorker {
             };
             app.update();
 
+            let feedback = app.world().resource::<Events<ActionFeedback>>();
+            for ActionFeedback { outcome, .. } in self.feedback_reader.read(feedback) {
+                for id in &self.subscriptions {
+                    scope.respond(*id, WorkerMessage::ActionFeedback(*outcome));
+                }
+            }
+            let room_responses = app.world().resource::<Events<RoomResponse>>();
+            for response in self.room_reader.read(room_responses) {
+                for id in &self.subscriptions {
+                    scope.respond(*id, WorkerMessage::Room(response.clone()));
+                }
+            }
+            let welcomes = app.world().resource::<Events<Welcome>>();
+            for welcome in self.welcome_reader.read(welcomes) {
+                for id in &self.subscriptions {
+                    scope.respond(*id, WorkerMessage::Welcome(welcome.clone()));
+                }
+            }
+            let shutdowns = app.world().resource::<Events<ServerShutdown>>();
+            for shutdown in self.shutdown_reader.read(shutdowns) {
+                for id in &self.subscriptions {
+                    scope.respond(*id, WorkerMessage::ServerShutdown(*shutdown));
+                }
+            }
+
             let Some((_, my_side)) = get_my_player(app.world_mut()) else {
                 return;
             };
@@ -82,20 +118,45 @@ impl Worker for BevyWorker {
                             my_side,
                             left_word: update.left_word.to_string(),
                             left_score: *update.left_score,
+                            left_meter: *update.left_meter,
+                            left_is_bot: update.left_is_bot,
                             right_word: update.right_word.to_string(),
                             right_score: *update.right_score,
+                            right_meter: *update.right_meter,
+                            right_is_bot: update.right_is_bot,
                             arena_size: update.arena_size,
+                            strike_rule: update.strike_rule,
+                            validation_mode: update.validation_mode,
+                            last_strike: update.last_strike,
+                            result: update.result,
                         }),
                     );
                 }
             }
         } else if let WorkerUpdateMessage::Token(token) = message {
-            let app = build_app(token);
-            self.game = Some(app);
+            let app = token.and_then(|token| {
+                build_app(token).map_err(|error| ConnectionError::Failed(error.to_string()))
+            });
+            match app {
+                Ok(app) => self.game = Some(app),
+                Err(error) => {
+                    for id in &self.subscriptions {
+                        scope.respond(*id, WorkerMessage::ConnectionFailed(error.clone()));
+                    }
+                }
+            }
         }
     }
 
-    fn received(&mut self, _: &WorkerScope<Self>, message: Self::Input, _: HandlerId) {
+    fn received(&mut self, scope: &WorkerScope<Self>, message: Self::Input, _: HandlerId) {
+        if let AppMessage::Connect(key) = message {
+            if self.game.is_none() {
+                scope.send_future(async {
+                    WorkerUpdateMessage::Token(fetch_server_token(key).await)
+                });
+            }
+            return;
+        }
         let Some(app) = self.game.as_mut() else {
             #[cfg(feature = "log")]
             log(format!(
@@ -105,10 +166,7 @@ impl Worker for BevyWorker {
             return;
         };
         let replicon_client = app.world().resource::<RepliconClient>();
-        let RepliconClientStatus::Connected {
-            client_id: Some(my_client_id),
-        } = replicon_client.status()
-        else {
+        let RepliconClientStatus::Connected { .. } = replicon_client.status() else {
             #[cfg(feature = "log")]
             log(format!(
                 "Discarding message received before client is connected: {:?}",
@@ -121,23 +179,23 @@ impl Worker for BevyWorker {
         let action: wordfight::Action = match message {
             AppMessage::AddLetter(letter) => wordfight::Action::Append(letter),
             AppMessage::Backspace => wordfight::Action::Delete,
+            AppMessage::SuperCollapse => wordfight::Action::SuperCollapse,
+            AppMessage::SuperExtend => wordfight::Action::SuperExtend,
+            AppMessage::Room(request) => {
+                app.world_mut().send_event(request);
+                app.update();
+                return;
+            }
+            AppMessage::Connect(_) => return,
         };
-        let mut query = app.world_mut().query::<(Entity, &PlayerSide, &Client)>();
-        let Some((my_player, my_side, _)) = query
-            .iter(app.world())
-            .find(|(_, _, client)| ***client == my_client_id)
-        else {
-            return;
-        };
-        let my_side = *my_side;
-        app.world_mut()
-            .send_event(action.made_by(my_player, my_side));
+        // the server works out which player this is from our connection
+        app.world_mut().send_event(ActionEvent::from(action));
         app.update();
     }
 }
 
 pub enum WorkerUpdateMessage {
-    Token(String),
+    Token(Result<String, ConnectionError>),
     Update,
 }
 
@@ -149,20 +207,17 @@ impl Drop for Interval {
     }
 }
 
-fn build_app(server_token: String) -> App {
-    let mut app = App::new();
-    let server_origin = SERVER_IP.unwrap_or(SERVER_DEFAULT_IP).to_string();
-    let server_port = SERVER_PORT.unwrap_or(SERVER_DEFAULT_PORT).to_string();
+fn build_app(server_token: String) -> Result<App, ClientError> {
+    let server_origin = SERVER_IP.unwrap_or(SERVER_DEFAULT_IP);
+    let server_port = SERVER_PORT.unwrap_or(SERVER_DEFAULT_PORT);
+    let client = ClientPlugin::new(server_origin, server_port, &server_token)?;
 
+    let mut app = App::new();
     app.add_plugins(WordFightPlugins);
-    app.add_plugins(ClientPlugin {
-        server_origin,
-        server_port,
-        server_token,
-    });
+    app.add_plugins(client);
     app.update();
     app.update();
-    app
+    Ok(app)
 }
 
 fn get_my_player(world: &mut World) -> Option<(Entity, PlayerSide)> {
@@ -186,20 +241,44 @@ extern "C" {
     fn fetch(input: &Request) -> Promise;
 }
 
-async fn fetch_server_token() -> Result<String, JsValue> {
+// `key` is the account to sign in as. Without one, the server signs us up as a new player.
+async fn fetch_server_token(key: Option<String>) -> Result<String, ConnectionError> {
     let server_origin = SERVER_ORIGIN.unwrap_or(SERVER_DEFAULT_ORIGIN);
     let server_token_port = SERVER_TOKENS_PORT.unwrap_or(SERVER_DEFAULT_TOKENS_PORT);
-    let server_url = format!("{server_origin}:{server_token_port}");
+    let server_url = format!("{server_origin}:{server_token_port}/token");
+    let failed = |error: JsValue| ConnectionError::Failed(format!("{error:?}"));
     let opts = RequestInit::new();
     opts.set_method("GET");
     opts.set_mode(RequestMode::Cors);
-    let request = Request::new_with_str_and_init(&server_url, &opts)?;
-
-    let response = JsFuture::from(fetch(&request)).await?;
+    let request = Request::new_with_str_and_init(&server_url, &opts).map_err(failed)?;
+    // sent as a header rather than in the url, which proxies and access logs keep
+    if let Some(key) = key {
+        request
+            .headers()
+            .set("Authorization", &format!("Bearer {key}"))
+            .map_err(failed)?;
+    }
 
-    assert!(response.is_instance_of::<Response>());
-    let response: Response = response.dyn_into().unwrap();
-    let text = JsFuture::from(response.text()?).await?;
-    log(text.as_string().unwrap());
-    Ok(text.as_string().unwrap())
+    let response: Response = JsFuture::from(fetch(&request))
+        .await
+        .and_then(|response| response.dyn_into())
+        .map_err(failed)?;
+    // the token server turns clients away with an empty body
+    match response.status() {
+        403 => return Err(ConnectionError::Banned),
+        503 => return Err(ConnectionError::ServerRestarting),
+        status if !response.ok() => {
+            return Err(ConnectionError::Failed(format!(
+                "the token server answered with status {status}"
+            )));
+        }
+        _ => {}
+    }
+    let text = JsFuture::from(response.text().map_err(failed)?)
+        .await
+        .map_err(failed)?;
+    #[cfg(feature = "log")]
+    log(format!("{text:?}"));
+    text.as_string()
+        .ok_or_else(|| ConnectionError::Failed("the server token is not text".to_string()))
 }
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-use game::{Arena, Game, GamePlayers, Score, Word};
+use game::{
+    Arena, Bot, Game, GamePlayers, GameResult, LastStrike, Meter, Score, Strike, StrikeRule,
+    ValidationMode, Word,
+};
 
 pub struct ActiveGamePlugin;
 
@@ -13,7 +16,9 @@ impl Plugin for ActiveGamePlugin {
             )
             .add_systems(
                 Update,
-                Self::trigger_game_update.run_if(resource_exists::<ActiveGame>),
+                (Self::clear_finished_game, Self::trigger_game_update)
+                    .chain()
+                    .distributive_run_if(resource_exists::<ActiveGame>),
             );
     }
 }
@@ -26,47 +31,75 @@ impl ActiveGamePlugin {
         }
     }
 
+    // once the active game is despawned, wait for the next one
+    fn clear_finished_game(
+        mut commands: Commands,
+        active_game: Res<ActiveGame>,
+        games: Query<(), With<Game>>,
+    ) {
+        if !games.contains(active_game.0) {
+            info!("Active game {} has ended", active_game.0);
+            commands.remove_resource::<ActiveGame>();
+        }
+    }
+
     fn trigger_game_update(
         mut commands: Commands,
         mut events: EventWriter<ActiveGameUpdate>,
         active_game: Res<ActiveGame>,
-        games: Query<(&GamePlayers, &Arena)>,
-        words: Query<(&Word, &Score)>,
-        updated_words: Query<(), Changed<Word>>,
-        updated_scores: Query<(), Changed<Score>>,
+        games: Query<(&GamePlayers, &Arena, Option<Ref<GameResult>>)>,
+        rules: Query<(&StrikeRule, &ValidationMode, &LastStrike)>,
+        words: Query<(Ref<Word>, Ref<Score>, Ref<Meter>)>,
+        bots: Query<(), With<Bot>>,
     ) {
-        let Some((game, players, arena)) = games
+        let player_changed = |player: Entity| {
+            words.get(player).is_ok_and(|(word, score, meter)| {
+                word.is_changed() || score.is_changed() || meter.is_changed()
+            })
+        };
+        let Some((game, players, arena, result)) = games
             .get(active_game.0)
             .ok()
-            .map(|(players, arena)| (active_game, players, arena))
-            .filter(|(game, players, _)| {
+            .map(|(players, arena, result)| (active_game, players, arena, result))
+            .filter(|(game, players, _, result)| {
                 game.is_changed()
-                    || updated_words.contains(players.left)
-                    || updated_words.contains(players.right)
-                    || updated_scores.contains(players.left)
-                    || updated_scores.contains(players.right)
+                    || result.as_ref().is_some_and(|result| result.is_changed())
+                    || player_changed(players.left)
+                    || player_changed(players.right)
             })
         else {
             return;
         };
+        // words are cleared by every strike, so a new last strike always comes with an update
+        let Ok((strike_rule, validation_mode, last_strike)) = rules.get(game.0) else {
+            return;
+        };
 
-        let (left_word, left_score) = words
+        let (left_word, left_score, left_meter) = words
             .get(players.left)
-            .map(|(word, score)| (word.clone(), *score))
+            .map(|(word, score, meter)| (word.clone(), *score, *meter))
             .expect("PlayerSide::Left to have a Word");
-        let (right_word, right_score) = words
+        let (right_word, right_score, right_meter) = words
             .get(players.right)
-            .map(|(word, score)| (word.clone(), *score))
+            .map(|(word, score, meter)| (word.clone(), *score, *meter))
             .expect("PlayerSide::Right should have a Word");
         let event = ActiveGameUpdate {
             game: game.0,
             arena_size: arena.size(),
+            strike_rule: *strike_rule,
+            validation_mode: *validation_mode,
+            last_strike: **last_strike,
             player_left: players.left,
             left_word,
             left_score,
+            left_meter,
+            left_is_bot: bots.contains(players.left),
             player_right: players.right,
             right_word,
             right_score,
+            right_meter,
+            right_is_bot: bots.contains(players.right),
+            result: result.map(|result| *result),
         };
         info!("Game update triggered: {event:?}");
         commands.trigger(event.clone());
@@ -84,10 +117,18 @@ pub struct ActiveGame(Entity);
 pub struct ActiveGameUpdate {
     pub game: Entity,
     pub arena_size: usize,
+    pub strike_rule: StrikeRule,
+    pub validation_mode: ValidationMode,
+    pub last_strike: Option<Strike>,
     pub player_left: Entity,
     pub left_word: Word,
     pub left_score: Score,
+    pub left_meter: Meter,
+    pub left_is_bot: bool,
     pub player_right: Entity,
     pub right_word: Word,
     pub right_score: Score,
+    pub right_meter: Meter,
+    pub right_is_bot: bool,
+    pub result: Option<GameResult>,
 }
@@ -0,0 +1,40 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use bot_controller::search_action;
+use game::{Arena, Letter, StrikeRule, Word, WordList};
+
+// positions from the start of a round up to just before contact, in a 7-size arena
+const POSITIONS: [(&str, &str); 5] = [
+    ("", ""),
+    ("p", "a"),
+    ("pa", "al"),
+    ("pal", "alp"),
+    ("patr", "alph"),
+];
+
+fn word(letters: &str) -> Word {
+    let mut word = Word::default();
+    for letter in letters.chars().filter_map(Letter::from_char) {
+        word.push(letter);
+    }
+    word
+}
+
+fn search(c: &mut Criterion) {
+    let words = WordList::default();
+    let arena = Arena::new(7);
+    let rule = StrikeRule::default();
+
+    // bots decide within a single server frame, so each of these should stay well under ~16ms
+    let mut group = c.benchmark_group("search_action");
+    for (mine, theirs) in POSITIONS {
+        let (mine, theirs) = (word(mine), word(theirs));
+        group.bench_function(format!("{mine}/{theirs}"), |b| {
+            b.iter(|| search_action(black_box(&mine), black_box(&theirs), &arena, &rule, &words))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, search);
+criterion_main!(benches);
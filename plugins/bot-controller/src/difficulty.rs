@@ -0,0 +1,132 @@
+use rand_core::RngCore;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use game::DictionaryName;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Reflect)]
+pub enum BotDifficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl BotDifficulty {
+    pub const ALL: [BotDifficulty; 3] = [
+        BotDifficulty::Easy,
+        BotDifficulty::Medium,
+        BotDifficulty::Hard,
+    ];
+
+    pub fn profile(&self) -> BotProfile {
+        match self {
+            BotDifficulty::Easy => BotProfile {
+                reaction_delay: Duration::from_millis(900),
+                reaction_jitter: Duration::from_millis(400),
+                typing_interval: Duration::from_millis(450),
+                common_words_only: true,
+                read_chance: 0.25,
+            },
+            BotDifficulty::Medium => BotProfile {
+                reaction_delay: Duration::from_millis(600),
+                reaction_jitter: Duration::from_millis(250),
+                typing_interval: Duration::from_millis(300),
+                common_words_only: false,
+                read_chance: 0.6,
+            },
+            BotDifficulty::Hard => BotProfile {
+                reaction_delay: Duration::from_millis(350),
+                reaction_jitter: Duration::from_millis(100),
+                typing_interval: Duration::from_millis(180),
+                common_words_only: false,
+                read_chance: 0.95,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for BotDifficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BotDifficulty::Easy => write!(f, "Easy"),
+            BotDifficulty::Medium => write!(f, "Medium"),
+            BotDifficulty::Hard => write!(f, "Hard"),
+        }
+    }
+}
+
+// How a bot behaves, tuned so that bots play at a human pace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Reflect)]
+pub struct BotProfile {
+    // how long the bot takes to respond when its opponent's word changes, give or take the jitter
+    pub reaction_delay: Duration,
+    pub reaction_jitter: Duration,
+    // how long the bot takes between letters while it is typing
+    pub typing_interval: Duration,
+    // only play words from the game dictionary's common companion (see `common_dictionary`)
+    pub common_words_only: bool,
+    // chance that the bot reads its opponent's word before making contact,
+    // rather than striking with its strongest letter and hoping for the best
+    pub read_chance: f32,
+}
+
+impl BotProfile {
+    // reaction delays are spread evenly across `reaction_delay` +/- `reaction_jitter`
+    pub fn sample_reaction_delay(&self, rng: &mut impl RngCore) -> Duration {
+        let jitter = self.reaction_jitter.mul_f32(2. * unit(rng));
+        (self.reaction_delay + jitter).saturating_sub(self.reaction_jitter)
+    }
+
+    pub fn sample_reads_opponent(&self, rng: &mut impl RngCore) -> bool {
+        unit(rng) < self.read_chance
+    }
+}
+
+// A bot's vocabulary can be restricted to the more common words in a dictionary, which are
+// loaded as a separate dictionary named after the original (e.g. `english-common`).
+// Bots use the full dictionary when there is no common one.
+pub fn common_dictionary(dictionary: &DictionaryName) -> DictionaryName {
+    DictionaryName::new(format!("{dictionary}-common"))
+}
+
+// uniform in [0, 1)
+fn unit(rng: &mut impl RngCore) -> f32 {
+    (rng.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_prng::WyRand;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_sample_profile() {
+        let mut rng = WyRand::from_seed([7; 8]);
+        for difficulty in BotDifficulty::ALL {
+            let profile = difficulty.profile();
+            for _ in 0..100 {
+                let delay = profile.sample_reaction_delay(&mut rng);
+                assert!(delay >= profile.reaction_delay - profile.reaction_jitter);
+                assert!(delay <= profile.reaction_delay + profile.reaction_jitter);
+            }
+        }
+
+        let never = BotProfile {
+            read_chance: 0.,
+            ..BotDifficulty::Hard.profile()
+        };
+        let always = BotProfile {
+            read_chance: 1.,
+            ..BotDifficulty::Easy.profile()
+        };
+        for _ in 0..100 {
+            assert!(!never.sample_reads_opponent(&mut rng));
+            assert!(always.sample_reads_opponent(&mut rng));
+        }
+    }
+}
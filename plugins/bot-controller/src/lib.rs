@@ -1,21 +1,516 @@
 use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::prelude::{EntropyComponent, EntropyPlugin, ForkableRng, GlobalEntropy};
+use bevy_replicon::prelude::Replicated;
+
+use game::{
+    Action, Arena, Bot, Dictionary, GamePlayers, GameResult, GameSettingsQuery, InGame, Letter,
+    Meter, PlayerAction, PlayerSide, Reach, Score, StrikeRule, Word, WordFightSystems, WordList,
+};
+
+mod difficulty;
+pub use difficulty::*;
+
+mod search;
+pub use search::*;
+
+mod strategy;
+pub use strategy::*;
+
+pub type BotEntropy = EntropyComponent<WyRand>;
 
 pub struct BotControllerPlugin;
 
 impl Plugin for BotControllerPlugin {
-    fn build(&self, _app: &mut App) {
-        //
+    fn build(&self, app: &mut App) {
+        // the server seeds its own entropy, as can tests that want reproducible bots
+        if !app.is_plugin_added::<EntropyPlugin<WyRand>>() {
+            app.add_plugins(EntropyPlugin::<WyRand>::default());
+        }
+        app.init_resource::<BotStrategies>();
+        app.observe(Self::seed_bot);
+        app.add_systems(
+            Update,
+            Self::take_bot_actions
+                .in_set(BotControllerSystems)
+                .before(WordFightSystems),
+        );
     }
 }
 
 impl BotControllerPlugin {
-    //
+    // every bot draws from its own rng, forked in spawn order so that bot matches can be replayed
+    fn seed_bot(
+        trigger: Trigger<OnAdd, BotController>,
+        mut commands: Commands,
+        mut entropy: ResMut<GlobalEntropy<WyRand>>,
+        bots: Query<&BotController>,
+        strategies: Res<BotStrategies>,
+    ) {
+        let bot = trigger.entity();
+        commands.entity(bot).insert(entropy.fork_rng());
+        if let Ok(controller) = bots.get(bot) {
+            if !strategies.contains(&controller.strategy) {
+                warn!(
+                    "Bot {bot} uses unknown strategy {}, so it will not act",
+                    controller.strategy
+                );
+            }
+        }
+    }
+
+    fn take_bot_actions(
+        time: Res<Time>,
+        mut bots: Query<(
+            Entity,
+            &mut BotController,
+            &mut BotEntropy,
+            &PlayerSide,
+            &InGame,
+        )>,
+        games: Query<(&Arena, &GamePlayers, GameSettingsQuery), Without<GameResult>>,
+        players: Query<(&Word, &Score, &Reach, &Meter)>,
+        dictionary: Dictionary,
+        strategies: Res<BotStrategies>,
+        mut actions: EventWriter<PlayerAction>,
+    ) {
+        for (bot, mut controller, mut rng, side, in_game) in &mut bots {
+            let Ok((arena, game_players, settings)) = games.get(**in_game) else {
+                continue;
+            };
+            let dictionary_name = settings.dictionary;
+            let opponent = match side {
+                PlayerSide::Left => game_players.right,
+                PlayerSide::Right => game_players.left,
+            };
+            let (
+                Ok((word, score, reach, meter)),
+                Ok((opponent_word, opponent_score, opponent_reach, _)),
+            ) = (players.get(bot), players.get(opponent))
+            else {
+                continue;
+            };
+            if controller.opponent_word.as_ref() != Some(opponent_word) {
+                controller.react(opponent_word, &mut rng);
+            }
+            if !controller.cooldown.tick(time.delta()).finished() {
+                continue;
+            }
+            let Some(strategy) = strategies.get(&controller.strategy) else {
+                continue;
+            };
+
+            let words = if controller.profile.common_words_only {
+                dictionary
+                    .dictionaries()
+                    .get(&common_dictionary(dictionary_name))
+                    .unwrap_or_else(|| dictionary.get(dictionary_name))
+            } else {
+                dictionary.get(dictionary_name)
+            };
+            let view = GameView {
+                word,
+                opponent_word,
+                arena,
+                score: **score,
+                opponent_score: **opponent_score,
+                reach: **reach,
+                opponent_reach: **opponent_reach,
+                meter: **meter,
+                strike_rule: *settings.strike_rule,
+                validation_mode: *settings.validation_mode,
+                win_condition: *settings.win_condition,
+                complete_word_bonus: *settings.complete_word_bonus,
+                words,
+                reading: controller.reading,
+            };
+            if let Some(action) = strategy.choose_action(&view) {
+                debug!("Bot {bot} chose {action:?}");
+                actions.send(PlayerAction {
+                    player: bot,
+                    action,
+                });
+                controller.cooldown =
+                    Timer::new(controller.profile.typing_interval, TimerMode::Once);
+            }
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[derive(SystemSet)]
 pub struct BotControllerSystems;
 
+// Lets the server play a PlayerSide on nobody's behalf. Bots act through the same
+// PlayerActions that client inputs are turned into, so they follow all the same rules.
 #[derive(Debug)]
 #[derive(Component, Reflect)]
-pub struct BotController;
+pub struct BotController {
+    difficulty: BotDifficulty,
+    profile: BotProfile,
+    // the name of the `BotStrategy` this bot plays with
+    strategy: String,
+    // time until the bot can act again
+    cooldown: Timer,
+    // the opponent's word as of the last time the bot reacted to it
+    opponent_word: Option<Word>,
+    // whether the bot read the opponent's word when it last reacted
+    reading: bool,
+}
+
+impl Default for BotController {
+    fn default() -> Self {
+        Self::new(BotDifficulty::default())
+    }
+}
+
+impl BotController {
+    pub fn new(difficulty: BotDifficulty) -> Self {
+        let profile = difficulty.profile();
+        Self {
+            difficulty,
+            profile,
+            strategy: GreedyStrategy::NAME.to_string(),
+            cooldown: Timer::new(profile.typing_interval, TimerMode::Once),
+            opponent_word: None,
+            reading: false,
+        }
+    }
+
+    pub fn with_profile(mut self, profile: BotProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: impl Into<String>) -> Self {
+        self.strategy = strategy.into();
+        self
+    }
+
+    pub fn bundle(self) -> impl Bundle {
+        (
+            Replicated,
+            Bot,
+            Name::new(format!("Bot ({})", self.difficulty)),
+            self,
+        )
+    }
+
+    pub fn difficulty(&self) -> BotDifficulty {
+        self.difficulty
+    }
+
+    pub fn profile(&self) -> &BotProfile {
+        &self.profile
+    }
+
+    pub fn strategy(&self) -> &str {
+        &self.strategy
+    }
+
+    // the opponent's word changed, so take a moment to notice and decide whether to read it
+    fn react(&mut self, opponent_word: &Word, rng: &mut BotEntropy) {
+        let delay = self.profile.sample_reaction_delay(rng);
+        self.cooldown = Timer::new(delay, TimerMode::Once);
+        self.reading = self.profile.sample_reads_opponent(rng);
+        self.opponent_word = Some(opponent_word.clone());
+    }
+}
+
+// Builds words that can reach the opponent. When `reading` the opponent, the bot only makes
+// contact with a letter that wins (returning None to wait instead); otherwise it strikes with
+// its strongest letter.
+pub fn choose_action(
+    word: &Word,
+    opponent_word: &Word,
+    arena: &Arena,
+    strike_rule: &StrikeRule,
+    words: &WordList,
+    reading: bool,
+) -> Option<Action> {
+    let prefix = word.to_string().to_lowercase();
+    let gap = arena
+        .size()
+        .saturating_sub(word.len() + opponent_word.len());
+    match gap {
+        // contact is already being resolved
+        0 => None,
+        // the next letter strikes
+        1 => next_letters(words, &prefix, 0)
+            .into_iter()
+            .map(|(letter, _)| letter)
+            .filter(|letter| {
+                !reading
+                    || strike_rule
+                        .compare(Some(*letter), opponent_word.last().copied(), words)
+                        .is_gt()
+            })
+            .max_by_key(|letter| strike_rule.power(*letter, words))
+            .map(Action::Append),
+        _ => {
+            // prefer letters that keep the most words long enough to reach the opponent
+            let mut options = next_letters(words, &prefix, prefix.len() + gap);
+            if options.is_empty() {
+                options = next_letters(words, &prefix, 0);
+            }
+            match options.into_iter().max_by_key(|(_, count)| *count) {
+                Some((letter, _)) => Some(Action::Append(letter)),
+                // a dead end: back off and try another word
+                None => (!word.is_empty()).then_some(Action::Delete),
+            }
+        }
+    }
+}
+
+// Letters that continue `prefix`, counting the words at least `min_len` long behind each one
+fn next_letters(words: &WordList, prefix: &str, min_len: usize) -> Vec<(Letter, usize)> {
+    let mut counts = [0usize; 26];
+    for continuation in words
+        .continuations(prefix)
+        .iter()
+        .filter(|word| word.len() >= min_len)
+    {
+        if let Some(letter) = continuation[prefix.len()..]
+            .chars()
+            .next()
+            .and_then(Letter::from_char)
+        {
+            counts[letter.index()] += 1;
+        }
+    }
+    Letter::ALL
+        .into_iter()
+        .filter(|letter| counts[letter.index()] > 0)
+        .map(|letter| (letter, counts[letter.index()]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    use game::{
+        Client, ClientId, Dictionaries, DictionaryName, GameLog, ReplayEvent, Score, SpawnGame,
+        WordFightGamePlugin,
+    };
+
+    fn word(letters: &str) -> Word {
+        let mut word = Word::default();
+        for letter in letters.chars().filter_map(Letter::from_char) {
+            word.push(letter);
+        }
+        word
+    }
+
+    fn bot_app(seed: u64, dictionaries: Dictionaries) -> App {
+        let mut app = App::new();
+        app.insert_resource(dictionaries);
+        app.add_plugins((
+            MinimalPlugins,
+            EntropyPlugin::<WyRand>::with_seed(seed.to_le_bytes()),
+            WordFightGamePlugin,
+            BotControllerPlugin,
+        ));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            50,
+        )));
+        app.update();
+        app
+    }
+
+    #[test]
+    fn test_choose_action() {
+        let words = WordList::new(["paltry", "patronize", "pal", "zebra"]);
+        let arena = Arena::new(7);
+        let rule = StrikeRule::Alphabetical;
+
+        // heads for the words that can reach the opponent
+        assert_eq!(
+            choose_action(&word("pa"), &word(""), &arena, &rule, &words, true),
+            Some(Action::Append(Letter::T))
+        );
+        // strikes when a winning letter is available
+        assert_eq!(
+            choose_action(&word("pal"), &word("abc"), &arena, &rule, &words, true),
+            Some(Action::Append(Letter::T))
+        );
+        // waits when every letter would lose
+        assert_eq!(
+            choose_action(&word("pal"), &word("azz"), &arena, &rule, &words, true),
+            None
+        );
+        // ...unless it has not read the opponent's word
+        assert_eq!(
+            choose_action(&word("pal"), &word("azz"), &arena, &rule, &words, false),
+            Some(Action::Append(Letter::T))
+        );
+        // backs off from dead ends
+        assert_eq!(
+            choose_action(&word("zebra"), &word(""), &arena, &rule, &words, true),
+            Some(Action::Delete)
+        );
+    }
+
+    // test that a bot plays a game through to scoring against an idle player
+    #[test]
+    fn test_bot_scores() {
+        let mut app = bot_app(0, Dictionaries::default());
+        let human = app
+            .world_mut()
+            .spawn(Client::from(ClientId::new(1)).bundle())
+            .id();
+        let bot = app
+            .world_mut()
+            .spawn(BotController::new(BotDifficulty::Hard).bundle())
+            .id();
+        app.world_mut().trigger(SpawnGame::new(7, human, bot));
+        app.update();
+
+        for _ in 0..100 {
+            app.update();
+        }
+        assert!(**app.world().get::<Score>(bot).unwrap() > 0);
+        assert_eq!(**app.world().get::<Score>(human).unwrap(), 0);
+    }
+
+    // test that easy bots stick to common words when there are any
+    #[test]
+    fn test_bot_vocabulary() {
+        let mut dictionaries = Dictionaries::empty();
+        dictionaries.insert(
+            DictionaryName::default().to_string(),
+            WordList::new(["alphabet", "paltry", "patronize", "zebra"]),
+        );
+        dictionaries.insert(
+            common_dictionary(&DictionaryName::default()).to_string(),
+            WordList::new(["patronize"]),
+        );
+        let mut app = bot_app(0, dictionaries);
+        let human = app
+            .world_mut()
+            .spawn(Client::from(ClientId::new(1)).bundle())
+            .id();
+        let bot = app
+            .world_mut()
+            .spawn(BotController::new(BotDifficulty::Easy).bundle())
+            .id();
+        app.world_mut().trigger(SpawnGame::new(7, human, bot));
+        app.update();
+
+        for _ in 0..200 {
+            app.update();
+        }
+        assert!(**app.world().get::<Score>(bot).unwrap() > 0);
+        let game = **app.world().get::<InGame>(bot).unwrap();
+        for event in app.world().get::<GameLog>(game).unwrap().events() {
+            if let ReplayEvent::Action { word, .. } = event {
+                assert!("PATRONIZE".starts_with(&word.to_string()));
+            }
+        }
+    }
+
+    // spells out "pal" and then waits, whatever the opponent does
+    struct SpellPal;
+
+    impl BotStrategy for SpellPal {
+        fn choose_action(&self, view: &GameView) -> Option<Action> {
+            [Letter::P, Letter::A, Letter::L]
+                .get(view.word.len())
+                .copied()
+                .map(Action::Append)
+        }
+    }
+
+    #[test]
+    fn test_custom_strategy() {
+        let mut app = bot_app(0, Dictionaries::default());
+        app.register_bot_strategy("pal", SpellPal);
+        let human = app
+            .world_mut()
+            .spawn(Client::from(ClientId::new(1)).bundle())
+            .id();
+        let bot = app
+            .world_mut()
+            .spawn(BotController::default().with_strategy("pal").bundle())
+            .id();
+        let lost = app
+            .world_mut()
+            .spawn(BotController::default().with_strategy("missing").bundle())
+            .id();
+        app.world_mut().trigger(SpawnGame::new(9, human, bot));
+        let other_human = app
+            .world_mut()
+            .spawn(Client::from(ClientId::new(2)).bundle())
+            .id();
+        app.world_mut()
+            .trigger(SpawnGame::new(9, lost, other_human));
+        app.update();
+
+        for _ in 0..100 {
+            app.update();
+        }
+        assert_eq!(app.world().get::<Word>(bot).unwrap().to_string(), "PAL");
+        assert!(app.world().get::<Word>(lost).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_game_view_gap() {
+        let (left, right, arena) = (word("PA"), word("LAP"), Arena::new(9));
+        let words = WordList::new(["pal"]);
+        let mut view = GameView {
+            word: &left,
+            opponent_word: &right,
+            arena: &arena,
+            score: 0,
+            opponent_score: 0,
+            reach: 0,
+            opponent_reach: 0,
+            meter: 0,
+            strike_rule: Default::default(),
+            validation_mode: Default::default(),
+            win_condition: Default::default(),
+            complete_word_bonus: Default::default(),
+            words: &words,
+            reading: true,
+        };
+        assert_eq!(view.gap(), 4);
+        view.reach = 2;
+        view.opponent_reach = 1;
+        assert_eq!(view.gap(), 1);
+        view.reach = 5;
+        assert_eq!(view.gap(), 0);
+    }
+
+    // plays two bots against each other, returning everything that happened
+    fn play_bot_match(seed: u64) -> Vec<ReplayEvent> {
+        let mut app = bot_app(seed, Dictionaries::default());
+        let left = app
+            .world_mut()
+            .spawn(BotController::new(BotDifficulty::Medium).bundle())
+            .id();
+        let right = app
+            .world_mut()
+            .spawn(BotController::new(BotDifficulty::Hard).bundle())
+            .id();
+        app.world_mut().trigger(SpawnGame::new(7, left, right));
+        app.update();
+
+        for _ in 0..400 {
+            app.update();
+        }
+        let game = **app.world().get::<InGame>(left).unwrap();
+        app.world().get::<GameLog>(game).unwrap().events().to_vec()
+    }
+
+    #[test]
+    fn test_bot_matches_are_reproducible() {
+        let events = play_bot_match(1);
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, ReplayEvent::Strike { .. })));
+        assert_eq!(events, play_bot_match(1));
+        assert_ne!(events, play_bot_match(2));
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::HashMap,
+};
+
+use game::{Action, Arena, Letter, StrikeRule, Word, WordList};
+
+use crate::{choose_action, next_letters};
+
+// how many letters away from contact the search starts looking ahead;
+// further out, the bot just heads for words that can reach its opponent
+pub const SEARCH_DEPTH: usize = 5;
+// chance that both players fill the last space on the same frame, clearing both words
+// without a point (i.e. `Strike::OverRange`)
+const SIMULTANEOUS_CONTACT_CHANCE: f32 = 0.1;
+// positions the search may visit, to fit in a frame. Split evenly between the letters the bot
+// could play, so that letters late in the alphabet are looked at as closely as early ones.
+const MAX_NODES: usize = 2_000;
+
+// Looks ahead at every way both players could continue their words, assuming the players take
+// turns and that the opponent plays their best reply, and picks the letter with the best
+// expected strike. Returns None to wait for the opponent instead.
+pub fn search_action(
+    word: &Word,
+    opponent_word: &Word,
+    arena: &Arena,
+    strike_rule: &StrikeRule,
+    words: &WordList,
+) -> Option<Action> {
+    search_with_budget(word, opponent_word, arena, strike_rule, words, MAX_NODES)
+}
+
+fn search_with_budget(
+    word: &Word,
+    opponent_word: &Word,
+    arena: &Arena,
+    strike_rule: &StrikeRule,
+    words: &WordList,
+    max_nodes: usize,
+) -> Option<Action> {
+    let gap = arena
+        .size()
+        .saturating_sub(word.len() + opponent_word.len());
+    if gap == 0 || gap > SEARCH_DEPTH {
+        return choose_action(word, opponent_word, arena, strike_rule, words, true);
+    }
+    let search = Search::new(strike_rule, words);
+    let mine = word.to_string().to_lowercase();
+    let theirs = opponent_word.to_string().to_lowercase();
+
+    if gap == 1 {
+        let (letter, value) = search.best_strike(&mine, &theirs)?;
+        return (value > search.wait_value(&mine, &theirs)).then_some(Action::Append(letter));
+    }
+    let letters = next_letters(words, &mine, 0);
+    let budget = max_nodes / letters.len().max(1);
+    // break ties in favor of keeping the most words available
+    letters
+        .into_iter()
+        .map(|(letter, count)| {
+            let value = -search.root_value(&theirs, &extend(&mine, letter), gap - 1, budget);
+            (letter, value, count)
+        })
+        .max_by(|(_, value1, count1), (_, value2, count2)| {
+            value1.total_cmp(value2).then(count1.cmp(count2))
+        })
+        .map(|(letter, ..)| Action::Append(letter))
+        .or_else(|| (!word.is_empty()).then_some(Action::Delete))
+}
+
+struct Search<'a> {
+    strike_rule: &'a StrikeRule,
+    words: &'a WordList,
+    // the letters that can follow each prefix seen so far, as bitsets of letter indices
+    letters: RefCell<HashMap<String, u32>>,
+    nodes: Cell<usize>,
+    budget: Cell<usize>,
+}
+
+impl<'a> Search<'a> {
+    fn new(strike_rule: &'a StrikeRule, words: &'a WordList) -> Self {
+        Self {
+            strike_rule,
+            words,
+            letters: RefCell::default(),
+            nodes: Cell::new(0),
+            budget: Cell::new(usize::MAX),
+        }
+    }
+
+    // `value`, visiting at most `budget` positions
+    fn root_value(&self, mover: &str, other: &str, gap: usize, budget: usize) -> f32 {
+        self.nodes.set(0);
+        self.budget.set(budget);
+        self.value(mover, other, gap, f32::NEG_INFINITY, f32::INFINITY)
+    }
+
+    // The expected strike for the player to move (+1 a point for them, -1 a point for their
+    // opponent), given both players' words and how many spaces are left between them.
+    fn value(&self, mover: &str, other: &str, gap: usize, mut alpha: f32, beta: f32) -> f32 {
+        self.nodes.set(self.nodes.get() + 1);
+        if self.nodes.get() > self.budget.get() {
+            // out of time, so judge the position by what the opponent could strike with now
+            return self.wait_value(mover, other);
+        }
+        if gap <= 1 {
+            let wait = self.wait_value(mover, other);
+            return match self.best_strike(mover, other) {
+                Some((_, strike)) => strike.max(wait),
+                None => wait,
+            };
+        }
+        let letters = self.letters(mover);
+        if letters.is_empty() {
+            // a dead end: the mover has to back off while their opponent closes in
+            return -1.;
+        }
+        let mut best = f32::NEG_INFINITY;
+        for letter in letters {
+            let value = -self.value(other, &extend(mover, letter), gap - 1, -beta, -alpha);
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    // the best letter the mover can strike with right now, and its expected value
+    fn best_strike(&self, mover: &str, other: &str) -> Option<(Letter, f32)> {
+        self.letters(mover)
+            .into_iter()
+            .map(|letter| (letter, self.strike_value(letter, other)))
+            .max_by(|(_, value1), (_, value2)| value1.total_cmp(value2))
+    }
+
+    // the mover holds back, leaving their opponent to strike if they can win
+    fn wait_value(&self, mover: &str, other: &str) -> f32 {
+        -self
+            .letters(other)
+            .into_iter()
+            .map(|letter| self.strike_value(letter, mover))
+            .fold(0., f32::max)
+    }
+
+    fn strike_value(&self, letter: Letter, other: &str) -> f32 {
+        let other_letter = other.chars().last().and_then(Letter::from_char);
+        let value = match self
+            .strike_rule
+            .compare(Some(letter), other_letter, self.words)
+        {
+            Ordering::Greater => 1.,
+            Ordering::Less => -1.,
+            Ordering::Equal => 0.,
+        };
+        value * (1. - SIMULTANEOUS_CONTACT_CHANCE)
+    }
+
+    fn letters(&self, prefix: &str) -> Vec<Letter> {
+        let mask = *self
+            .letters
+            .borrow_mut()
+            .entry(prefix.to_string())
+            .or_insert_with(|| letter_set(self.words, prefix));
+        Letter::ALL
+            .into_iter()
+            .filter(|letter| mask & (1 << letter.index()) != 0)
+            .collect()
+    }
+}
+
+// Every letter that continues `prefix`. Continuations are sorted, so this skips from one
+// next letter to the next rather than looking at every word.
+fn letter_set(words: &WordList, prefix: &str) -> u32 {
+    let mut mask = 0;
+    let mut rest = words.continuations(prefix);
+    while let Some(word) = rest.first() {
+        let next = word.as_bytes().get(prefix.len()).copied();
+        if let Some(letter) = next.and_then(|byte| Letter::from_char(byte as char)) {
+            mask |= 1 << letter.index();
+        }
+        rest = &rest
+            [rest.partition_point(|word| word.as_bytes().get(prefix.len()).copied() <= next)..];
+    }
+    mask
+}
+
+fn extend(prefix: &str, letter: Letter) -> String {
+    format!("{prefix}{}", letter.to_string().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(letters: &str) -> Word {
+        let mut word = Word::default();
+        for letter in letters.chars().filter_map(Letter::from_char) {
+            word.push(letter);
+        }
+        word
+    }
+
+    // every word of `len` letters made from `letters`
+    fn all_words(letters: &str, len: usize) -> Vec<String> {
+        (0..len).fold(vec![String::new()], |words, _| {
+            words
+                .iter()
+                .flat_map(|word| letters.chars().map(move |letter| format!("{word}{letter}")))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_search_budget() {
+        // the strongest words start with the letters the search looks at last
+        let mut dictionary = all_words("abcdefghijklmnop", 4);
+        dictionary.extend(all_words("wxyz", 4));
+        let words = WordList::new(dictionary);
+        let arena = Arena::new(5);
+        let rule = StrikeRule::Alphabetical;
+        let (mine, theirs) = (word(""), word("c"));
+
+        // big enough that the search runs out of positions to visit
+        let search = Search::new(&rule, &words);
+        let nodes: usize = search
+            .letters("")
+            .into_iter()
+            .map(|letter| {
+                search.root_value("c", &extend("", letter), 3, usize::MAX);
+                search.nodes.get()
+            })
+            .sum();
+        assert!(nodes > MAX_NODES);
+        assert_eq!(
+            search_action(&mine, &theirs, &arena, &rule, &words),
+            search_with_budget(&mine, &theirs, &arena, &rule, &words, usize::MAX)
+        );
+    }
+
+    #[test]
+    fn test_search_action() {
+        let words = WordList::new(["cabin", "cable", "cabal", "cays", "xwing"]);
+        let arena = Arena::new(5);
+        let rule = StrikeRule::Alphabetical;
+
+        // "cab" leaves the opponent free to strike with W, while "cay" holds them off
+        assert_eq!(
+            choose_action(&word("ca"), &word("x"), &arena, &rule, &words, true),
+            Some(Action::Append(Letter::B))
+        );
+        assert_eq!(
+            search_action(&word("ca"), &word("x"), &arena, &rule, &words),
+            Some(Action::Append(Letter::Y))
+        );
+        // strikes when the next letter wins, and waits when it would lose
+        assert_eq!(
+            search_action(&word("cab"), &word("a"), &arena, &rule, &words),
+            Some(Action::Append(Letter::L))
+        );
+        assert_eq!(
+            search_action(&word("cay"), &word("x"), &arena, &rule, &words),
+            None
+        );
+    }
+}
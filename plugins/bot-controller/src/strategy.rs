@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use game::{
+    Action, Arena, CompleteWordBonus, StrikeRule, ValidationMode, WinCondition, Word, WordList,
+};
+
+use crate::{choose_action, search_action};
+
+// Decides what a bot does next. Strategies are registered by name (see
+// `BotStrategyAppExt::register_bot_strategy`) and picked per bot with
+// `BotController::with_strategy`.
+pub trait BotStrategy: Send + Sync + 'static {
+    // Called whenever the bot is ready to act. Returning None waits until it is ready again.
+    fn choose_action(&self, view: &GameView) -> Option<Action>;
+}
+
+// Everything a bot can see of its game when choosing an action.
+#[derive(Clone, Copy, Debug)]
+pub struct GameView<'a> {
+    pub word: &'a Word,
+    pub opponent_word: &'a Word,
+    pub arena: &'a Arena,
+    pub score: usize,
+    pub opponent_score: usize,
+    // empty slots each word reaches across after a SuperExtend, see `game::Reach`
+    pub reach: usize,
+    pub opponent_reach: usize,
+    // what the bot can spend on special actions, see `game::Meter`
+    pub meter: usize,
+    pub strike_rule: StrikeRule,
+    pub validation_mode: ValidationMode,
+    pub win_condition: WinCondition,
+    pub complete_word_bonus: CompleteWordBonus,
+    // the words the bot knows, which may only be the common words of the game's dictionary
+    pub words: &'a WordList,
+    // whether the bot noticed its opponent's last move (see `BotProfile::read_chance`);
+    // strategies that want to play at the bot's difficulty should ignore the opponent otherwise
+    pub reading: bool,
+}
+
+impl GameView<'_> {
+    // how many empty spaces are left between the two words (and whatever they reach across)
+    pub fn gap(&self) -> usize {
+        self.arena.size().saturating_sub(
+            self.word.len() + self.reach + self.opponent_word.len() + self.opponent_reach,
+        )
+    }
+}
+
+// Heads for whatever keeps the most words open, see `choose_action`
+pub struct GreedyStrategy;
+
+impl GreedyStrategy {
+    pub const NAME: &'static str = "greedy";
+}
+
+impl BotStrategy for GreedyStrategy {
+    fn choose_action(&self, view: &GameView) -> Option<Action> {
+        choose_action(
+            view.word,
+            view.opponent_word,
+            view.arena,
+            &view.strike_rule,
+            view.words,
+            view.reading,
+        )
+    }
+}
+
+// Looks ahead at how both players' words could play out, see `search_action`
+pub struct SearchStrategy;
+
+impl SearchStrategy {
+    pub const NAME: &'static str = "search";
+}
+
+impl BotStrategy for SearchStrategy {
+    fn choose_action(&self, view: &GameView) -> Option<Action> {
+        // even experts can miss what their opponent is doing
+        if !view.reading {
+            return GreedyStrategy.choose_action(view);
+        }
+        search_action(
+            view.word,
+            view.opponent_word,
+            view.arena,
+            &view.strike_rule,
+            view.words,
+        )
+    }
+}
+
+// Every strategy bots can play with, by name.
+#[derive(Resource)]
+pub struct BotStrategies(HashMap<String, Box<dyn BotStrategy>>);
+
+impl Default for BotStrategies {
+    fn default() -> Self {
+        let mut strategies = BotStrategies(HashMap::default());
+        strategies.insert(GreedyStrategy::NAME, GreedyStrategy);
+        strategies.insert(SearchStrategy::NAME, SearchStrategy);
+        strategies
+    }
+}
+
+impl BotStrategies {
+    pub fn insert(&mut self, name: impl Into<String>, strategy: impl BotStrategy) {
+        self.0.insert(name.into(), Box::new(strategy));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn BotStrategy> {
+        self.0.get(name).map(Box::as_ref)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+pub trait BotStrategyAppExt {
+    // Makes `strategy` available to bots as `name`, replacing any strategy with that name.
+    fn register_bot_strategy(
+        &mut self,
+        name: impl Into<String>,
+        strategy: impl BotStrategy,
+    ) -> &mut Self;
+}
+
+impl BotStrategyAppExt for App {
+    fn register_bot_strategy(
+        &mut self,
+        name: impl Into<String>,
+        strategy: impl BotStrategy,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(BotStrategies::default)
+            .insert(name, strategy);
+        self
+    }
+}
@@ -16,22 +16,31 @@ pub use bevy_replicon;
 pub use bevy_replicon_renet2;
 
 mod transport;
+pub use transport::ClientError;
+use transport::ClientTransportPlugin;
 
 pub struct ClientPlugin {
-    pub server_origin: String,
-    pub server_port: String,
-    pub server_token: String,
+    transport: ClientTransportPlugin,
+}
+
+impl ClientPlugin {
+    // Fails if the server's address or the token fetched from it cannot be used to connect.
+    pub fn new(
+        server_origin: &str,
+        server_port: &str,
+        server_token: &str,
+    ) -> Result<Self, ClientError> {
+        Ok(Self {
+            transport: ClientTransportPlugin::new(server_origin, server_port, server_token)?,
+        })
+    }
 }
 
 impl Plugin for ClientPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(RepliconRenetClientPlugin);
 
-        app.add_plugins(transport::ClientTransportPlugin::new(
-            &self.server_origin,
-            &self.server_port,
-            &self.server_token,
-        ));
+        app.add_plugins(self.transport.clone());
         app.add_systems(Startup, |mut commands: Commands| {
             commands.add(ClientCommand::Connect);
         });
@@ -1,47 +1,57 @@
+use base64::Engine;
 use std::net::SocketAddr;
+use thiserror::Error;
 use url::Url;
 
 use bevy::prelude::{App, Plugin};
-use renet2::transport::WebServerDestination;
+use renet2::transport::{ConnectToken, NetcodeError, WebServerDestination};
 
-use game::PROTOCOL_ID;
+use game::ServerToken;
 
+#[derive(Clone)]
 pub struct ClientTransportPlugin {
     server_address: WebServerDestination,
-    server_token: String,
+    cert_hash: Vec<u8>,
+    connect_token: ConnectToken,
 }
 
 impl ClientTransportPlugin {
-    pub fn new(host: &str, port: &str, server_token: &str) -> Self {
-        Self::ip(host, port, server_token)
-            // .or_else(|| Self::url(host, port, server_token))
-            .unwrap()
+    pub fn new(host: &str, port: &str, server_token: &str) -> Result<Self, ClientError> {
+        let server_address = Self::ip(host, port)
+            // .or_else(|| Self::url(host, port))
+            .ok_or_else(|| ClientError::InvalidAddress(format!("{host}:{port}")))?;
+
+        // the server picks our client id and signs it into the connect token
+        let token = base64::engine::general_purpose::STANDARD.decode(server_token)?;
+        let ServerToken {
+            cert_hash,
+            connect_token,
+        } = bincode::deserialize(&token)?;
+        let connect_token = ConnectToken::read(&mut connect_token.as_slice())?;
+        Ok(Self {
+            server_address,
+            cert_hash,
+            connect_token,
+        })
     }
 
-    fn _url(host: &str, port: &str, server_token: &str) -> Option<Self> {
+    fn _url(host: &str, port: &str) -> Option<WebServerDestination> {
         format!("{host}:{port}")
             .parse::<Url>()
-            .map(|url| Self {
-                server_address: WebServerDestination::Url(url),
-                server_token: server_token.to_string(),
-            })
+            .map(WebServerDestination::Url)
             .ok()
     }
 
-    fn ip(ip: &str, port: &str, server_token: &str) -> Option<Self> {
+    fn ip(ip: &str, port: &str) -> Option<WebServerDestination> {
         format!("{ip}:{port}")
             .parse::<SocketAddr>()
-            .map(|addr| Self {
-                server_address: WebServerDestination::Addr(addr),
-                server_token: server_token.to_string(),
-            })
+            .map(WebServerDestination::Addr)
             .ok()
     }
 }
 
 impl Plugin for ClientTransportPlugin {
     fn build(&self, app: &mut App) {
-        use base64::Engine;
         use renet2::transport::ClientAuthentication;
         #[cfg(target_family = "wasm")]
         use renet2::transport::{
@@ -53,29 +63,18 @@ impl Plugin for ClientTransportPlugin {
         let current_time = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap();
-        let client_id = current_time.as_millis() as u64;
-        let authentication = ClientAuthentication::Unsecure {
-            client_id,
-            protocol_id: PROTOCOL_ID,
-            socket_id: 0,
-            server_addr,
-            user_data: None,
+        let cert_hash = self.cert_hash.clone();
+        let authentication = ClientAuthentication::Secure {
+            connect_token: self.connect_token.clone(),
         };
 
-        let hash = base64::engine::general_purpose::STANDARD
-            .decode(self.server_token.clone())
-            .unwrap();
-        #[cfg(not(target_family = "wasm"))]
-        let _ = hash;
         #[cfg(not(target_family = "wasm"))]
-        let _ = authentication;
-        #[cfg(not(target_family = "wasm"))]
-        let _ = app;
+        let _ = (server_addr, current_time, cert_hash, authentication, app);
 
         #[cfg(target_family = "wasm")]
         let config = WebTransportClientConfig::new_with_certs(
             server_addr,
-            Vec::from([ServerCertHash::try_from(hash).unwrap()]),
+            Vec::from([ServerCertHash::try_from(cert_hash).unwrap()]),
         );
         #[cfg(target_family = "wasm")]
         let socket = WebTransportClient::new(config);
@@ -85,3 +84,16 @@ impl Plugin for ClientTransportPlugin {
         app.insert_resource(transport);
     }
 }
+
+// Why the client could not be set up to connect to the server.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Server address {0} is invalid")]
+    InvalidAddress(String),
+    #[error("Server token is not base64: {0}")]
+    TokenEncoding(#[from] base64::DecodeError),
+    #[error("Server token could not be read: {0}")]
+    TokenContents(#[from] bincode::Error),
+    #[error("Server token does not contain a valid connect token: {0}")]
+    ConnectToken(#[from] NetcodeError),
+}
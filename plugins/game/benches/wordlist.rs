@@ -0,0 +1,75 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use game::WordList;
+
+// a mix of short/long prefixes, including ones that are not in the dictionary
+const PREFIXES: [&str; 6] = ["p", "pal", "patro", "alphabe", "zyzzyv", "qxj"];
+
+// the original implementation, kept around as a baseline
+fn scan(words: &WordList, prefix: &str) -> bool {
+    words.words().iter().any(|word| word.starts_with(prefix))
+}
+
+fn prefix_lookup(c: &mut Criterion) {
+    let words = WordList::default();
+
+    let mut group = c.benchmark_group("is_prefix");
+    for prefix in PREFIXES {
+        group.bench_function(format!("index/{prefix}"), |b| {
+            b.iter(|| words.is_prefix(black_box(prefix)))
+        });
+        group.bench_function(format!("scan/{prefix}"), |b| {
+            b.iter(|| scan(&words, black_box(prefix)))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("continuations");
+    for prefix in PREFIXES {
+        group.bench_function(format!("index/{prefix}"), |b| {
+            b.iter(|| words.continuations(black_box(prefix)).len())
+        });
+        group.bench_function(format!("scan/{prefix}"), |b| {
+            b.iter(|| {
+                words
+                    .words()
+                    .iter()
+                    .filter(|word| word.starts_with(black_box(prefix)))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+// infixes that only appear in the middle of words, plus ones that appear nowhere
+const INFIXES: [&str; 5] = ["ltr", "onize", "phab", "zzyv", "qxj"];
+
+fn infix_lookup(c: &mut Criterion) {
+    let words = WordList::default();
+
+    c.bench_function("infix_index/build", |b| {
+        b.iter(|| WordList::new(words.words()).is_infix(black_box("ltr")))
+    });
+
+    // build the index once before measuring lookups
+    words.is_infix("");
+    let mut group = c.benchmark_group("is_infix");
+    for infix in INFIXES {
+        group.bench_function(format!("index/{infix}"), |b| {
+            b.iter(|| words.is_infix(black_box(infix)))
+        });
+        group.bench_function(format!("scan/{infix}"), |b| {
+            b.iter(|| {
+                words
+                    .words()
+                    .iter()
+                    .any(|word| word.contains(black_box(infix)))
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, prefix_lookup, infix_lookup);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use bevy::prelude::*;
+
+use crate::Rating;
+
+// A player's lasting identity on the server, which (unlike their `Client`) survives reconnects
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Component, Deref, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct PlayerId(u64);
+
+impl PlayerId {
+    pub fn new(id: u64) -> Self {
+        PlayerId(id)
+    }
+}
+
+impl std::fmt::Display for PlayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Sent to a client once it connects, with the account it is playing as. Clients keep `key`
+// and pass it along when fetching their next connect token to sign in as the same player.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct Welcome {
+    pub player: PlayerId,
+    pub name: String,
+    pub rating: Rating,
+    pub key: String,
+}
@@ -2,35 +2,140 @@ use serde::{Deserialize, Serialize};
 
 use bevy::prelude::*;
 
-use crate::{Dictionary, Letter, Word};
+use crate::{
+    Arena, Letter, Meter, Reach, ValidationMode, Word, WordList, COLLAPSE_LETTERS, EXTEND_SLOTS,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[derive(Reflect)]
 #[derive(Serialize, Deserialize)]
 pub enum Action {
     Append(Letter),
     Delete,
-    // SuperCollapse,
-    // SuperExtend,
+    // knocks the last few letters off the opponent's word
+    SuperCollapse,
+    // reaches across a few empty arena slots, forcing a strike if that closes the gap
+    SuperExtend,
 }
-use Action::{Append, Delete};
+use Action::{Append, Delete, SuperCollapse, SuperExtend};
 
 impl Action {
-    pub fn apply(&self, word: &mut Word, dictionary: &Dictionary) {
+    // the meter cost of an action; regular actions are free
+    pub fn cost(&self) -> usize {
         match self {
+            Append(_) | Delete => 0,
+            SuperCollapse => 2,
+            SuperExtend => 3,
+        }
+    }
+
+    pub fn is_special(&self) -> bool {
+        self.cost() > 0
+    }
+
+    pub fn apply(
+        &self,
+        word: &mut Word,
+        arena: &Arena,
+        words: &WordList,
+        mode: ValidationMode,
+    ) -> ActionOutcome {
+        match self {
+            Append(_) if word.len() >= arena.size() => {
+                info!("{word} already fills the arena");
+                ActionOutcome::ArenaFull
+            }
             Append(letter) => {
                 let test_string = format!("{}{}", word, letter);
-                if dictionary.is_word_substring(test_string.as_str()) {
+                if words.is_word_substring(test_string.as_str(), mode) {
                     word.push(*letter);
                     info!("Added {letter}, making {word}");
+                    ActionOutcome::Accepted
                 } else {
                     info!("{word}{letter} is not in the dictionary",);
+                    ActionOutcome::NotAPrefix
                 }
             }
-            Delete => {
-                let removed_letter = word.pop();
-                info!("Removed {removed_letter:?} from {word}");
+            Delete => match word.pop() {
+                Some(removed_letter) => {
+                    info!("Removed {removed_letter} from {word}");
+                    ActionOutcome::Accepted
+                }
+                None => ActionOutcome::WordEmpty,
+            },
+            SuperCollapse | SuperExtend => {
+                error!("Special action {self:?} needs both players, use apply_special");
+                ActionOutcome::Unavailable
             }
         }
     }
+
+    // Special actions also act on the opponent's word, and are paid for from the meter
+    // only once they are known to do something.
+    pub fn apply_special(
+        &self,
+        word: &Word,
+        meter: &mut Meter,
+        reach: &mut Reach,
+        opponent_word: &mut Word,
+        arena: &Arena,
+    ) -> ActionOutcome {
+        let mut charged = *meter;
+        if !charged.spend(self.cost()) {
+            return ActionOutcome::MeterTooLow;
+        }
+        let outcome = match self {
+            SuperCollapse if opponent_word.is_empty() => ActionOutcome::Unavailable,
+            SuperCollapse => {
+                let remaining = opponent_word.len().saturating_sub(COLLAPSE_LETTERS);
+                opponent_word.truncate(remaining);
+                info!("Collapsed opponent word to {opponent_word}");
+                ActionOutcome::Accepted
+            }
+            SuperExtend if word.is_empty() => ActionOutcome::WordEmpty,
+            SuperExtend => {
+                let gap = arena
+                    .size()
+                    .saturating_sub(word.len() + **reach + opponent_word.len());
+                if gap == 0 {
+                    ActionOutcome::ArenaFull
+                } else {
+                    let slots = gap.min(EXTEND_SLOTS);
+                    **reach += slots;
+                    info!("Extended {word} across {slots} empty slots");
+                    ActionOutcome::Accepted
+                }
+            }
+            Append(_) | Delete => {
+                error!("Regular action {self:?} does not use the meter, use apply");
+                ActionOutcome::Unavailable
+            }
+        };
+        if outcome.is_accepted() {
+            *meter = charged;
+        }
+        outcome
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub enum ActionOutcome {
+    Accepted,
+    // the new letter does not continue any word in the dictionary
+    NotAPrefix,
+    // there is nothing to delete
+    WordEmpty,
+    // the word already spans the whole arena
+    ArenaFull,
+    // the meter does not hold enough to pay for a special action
+    MeterTooLow,
+    // the special action would have no effect right now
+    Unavailable,
+}
+
+impl ActionOutcome {
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, ActionOutcome::Accepted)
+    }
 }
@@ -4,13 +4,16 @@ use thiserror::Error;
 
 use bevy::{ecs::entity::MapEntities, prelude::*};
 
-use crate::{PlayerSide, Word};
+use crate::{GameSettings, PlayerSide, RoundsPlayed, StrikeRule, Word, WordList};
 
 #[derive(Bundle)]
 pub struct GameBundle {
     game: Game,
     players: GamePlayers,
     arena: Arena,
+    settings: GameSettings,
+    rounds: RoundsPlayed,
+    last_strike: LastStrike,
 }
 
 impl GameBundle {
@@ -19,8 +22,16 @@ impl GameBundle {
             game: Game,
             players: GamePlayers { left, right },
             arena: Arena::new(arena_size),
+            settings: GameSettings::default(),
+            rounds: RoundsPlayed::default(),
+            last_strike: LastStrike::default(),
         }
     }
+
+    pub fn with_settings(mut self, settings: GameSettings) -> Self {
+        self.settings = settings;
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -59,8 +70,26 @@ impl Arena {
         self.size
     }
 
-    pub fn strike(&self, left_word: &Word, right_word: &Word) -> Result<Strike, ArenaError> {
-        let total_letters = left_word.len() + right_word.len();
+    pub fn strike(
+        &self,
+        left_word: &Word,
+        right_word: &Word,
+        rule: &StrikeRule,
+        words: &WordList,
+    ) -> Result<Strike, ArenaError> {
+        self.strike_with_reach(left_word, right_word, 0, rule, words)
+    }
+
+    // `reach` counts empty slots that the players' words extend across, as if they were letters
+    pub fn strike_with_reach(
+        &self,
+        left_word: &Word,
+        right_word: &Word,
+        reach: usize,
+        rule: &StrikeRule,
+        words: &WordList,
+    ) -> Result<Strike, ArenaError> {
+        let total_letters = left_word.len() + right_word.len() + reach;
         match total_letters.cmp(&self.size) {
             Ordering::Greater => {
                 return Ok(Strike::OverRange);
@@ -75,22 +104,74 @@ impl Arena {
             _ => {}
         };
 
-        match left_word.last().cmp(&right_word.last()) {
-            Ordering::Less => Ok(Strike::Score(PlayerSide::Right)),
-            Ordering::Greater => Ok(Strike::Score(PlayerSide::Left)),
+        match rule.compare(left_word.last().copied(), right_word.last().copied(), words) {
+            Ordering::Less => Ok(Strike::Score(PlayerSide::Right, None)),
+            Ordering::Greater => Ok(Strike::Score(PlayerSide::Left, None)),
             // one of the two must be Some, so this cannot be the None == None case.
             Ordering::Equal => Ok(Strike::Parry),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Reflect)]
+#[derive(Deserialize, Serialize)]
 pub enum Strike {
-    Score(PlayerSide),
+    Score(PlayerSide, Option<StrikeBonus>),
     Parry,
     OverRange,
 }
 
+impl Strike {
+    pub fn winner(&self) -> Option<PlayerSide> {
+        match self {
+            Strike::Score(side, _) => Some(*side),
+            Strike::Parry | Strike::OverRange => None,
+        }
+    }
+
+    // how many points the winner scores
+    pub fn points(&self) -> usize {
+        match self {
+            Strike::Score(_, Some(StrikeBonus::CompleteWord)) => 2,
+            Strike::Score(..) => 1,
+            Strike::Parry | Strike::OverRange => 0,
+        }
+    }
+}
+
+// Why a strike was decided by something other than (or in addition to) its letters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Reflect)]
+#[derive(Deserialize, Serialize)]
+pub enum StrikeBonus {
+    // the winner struck with a complete word and scores an extra point
+    CompleteWord,
+    // the letters tied, but only the winner struck with a complete word
+    TieBreak,
+}
+
+impl std::fmt::Display for StrikeBonus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StrikeBonus::CompleteWord => write!(f, "Complete word!"),
+            StrikeBonus::TieBreak => write!(f, "Complete word breaks the tie!"),
+        }
+    }
+}
+
+// The last strike in a game, so that clients can show how it was decided.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Component, Deref, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct LastStrike(Option<Strike>);
+
+impl LastStrike {
+    pub fn new(strike: Strike) -> Self {
+        LastStrike(Some(strike))
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ArenaError {
     #[error("Player cursors {left} and {right} out of range on arena size: {total}")]
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+
+use crate::{CompleteWordBonus, PlayerSide};
+
+// How a game is won. Lives on the Game entity next to its Arena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Component, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub enum WinCondition {
+    // the first player to score this many points wins
+    Points(usize),
+    // the game lasts this many rounds (strikes), and the player with more points wins.
+    // ends early once the trailing player can no longer catch up, even scoring every
+    // remaining round with the most points a strike can be worth.
+    Rounds(usize),
+}
+
+impl Default for WinCondition {
+    fn default() -> Self {
+        WinCondition::Points(5)
+    }
+}
+
+impl WinCondition {
+    pub fn result(
+        &self,
+        rounds_played: usize,
+        left_score: usize,
+        right_score: usize,
+        bonus: CompleteWordBonus,
+    ) -> Option<GameResult> {
+        let finished = match self {
+            WinCondition::Points(target) => left_score >= *target || right_score >= *target,
+            WinCondition::Rounds(total) => {
+                let remaining = total.saturating_sub(rounds_played);
+                remaining == 0 || left_score.abs_diff(right_score) > remaining * bonus.max_points()
+            }
+        };
+        if !finished {
+            return None;
+        }
+        let winner = match left_score.cmp(&right_score) {
+            Ordering::Greater => Some(PlayerSide::Left),
+            Ordering::Less => Some(PlayerSide::Right),
+            Ordering::Equal => None,
+        };
+        Some(GameResult {
+            winner,
+            left_score,
+            right_score,
+            aborted: false,
+            forfeit: false,
+        })
+    }
+}
+
+impl std::fmt::Display for WinCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WinCondition::Points(target) => write!(f, "First to {target}"),
+            WinCondition::Rounds(total) => write!(f, "{total} rounds"),
+        }
+    }
+}
+
+// Every strike (including parries) counts as a round.
+#[derive(Clone, Copy, Debug, Default)]
+#[derive(Component, Deref, DerefMut, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct RoundsPlayed(usize);
+
+// Inserted on the Game entity once it has ended. A `None` winner is a draw.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Component, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct GameResult {
+    pub winner: Option<PlayerSide>,
+    pub left_score: usize,
+    pub right_score: usize,
+    // the server called the game off before it was decided (e.g. to shut down),
+    // so it has no winner and does not count towards anyone's rating
+    #[serde(default)]
+    pub aborted: bool,
+    // the loser left before the game was decided, so the winner won by default
+    #[serde(default)]
+    pub forfeit: bool,
+}
+
+impl GameResult {
+    pub fn aborted(left_score: usize, right_score: usize) -> Self {
+        GameResult {
+            winner: None,
+            left_score,
+            right_score,
+            aborted: true,
+            forfeit: false,
+        }
+    }
+
+    pub fn forfeit(winner: PlayerSide, left_score: usize, right_score: usize) -> Self {
+        GameResult {
+            winner: Some(winner),
+            left_score,
+            right_score,
+            aborted: false,
+            forfeit: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+pub struct GameEnded {
+    pub game: Entity,
+    pub result: GameResult,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_win_condition_points() {
+        let condition = WinCondition::Points(3);
+        let bonus = CompleteWordBonus::Off;
+        assert_eq!(condition.result(4, 2, 2, bonus), None);
+        let result = condition.result(5, 3, 2, bonus).unwrap();
+        assert_eq!(result.winner, Some(PlayerSide::Left));
+        assert_eq!((result.left_score, result.right_score), (3, 2));
+    }
+
+    #[test]
+    fn test_win_condition_rounds() {
+        let condition = WinCondition::Rounds(5);
+        let bonus = CompleteWordBonus::Off;
+        assert_eq!(condition.result(2, 1, 1, bonus), None);
+        // 2 rounds left cannot make up a 3 point difference
+        let result = condition.result(3, 0, 3, bonus).unwrap();
+        assert_eq!(result.winner, Some(PlayerSide::Right));
+        // parries can leave the game tied once all rounds are played
+        let result = condition.result(5, 2, 2, bonus).unwrap();
+        assert_eq!(result.winner, None);
+    }
+
+    #[test]
+    fn test_win_condition_rounds_extra_point() {
+        let condition = WinCondition::Rounds(5);
+        // complete words can score 2 points a round, so 2 rounds can make up 3 points...
+        assert_eq!(
+            condition.result(3, 0, 3, CompleteWordBonus::ExtraPoint),
+            None
+        );
+        // ...but not 5
+        let result = condition
+            .result(3, 0, 5, CompleteWordBonus::ExtraPoint)
+            .unwrap();
+        assert_eq!(result.winner, Some(PlayerSide::Right));
+    }
+}
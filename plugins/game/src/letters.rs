@@ -20,6 +20,17 @@ impl std::fmt::Display for Letter {
 }
 
 impl Letter {
+    #[rustfmt::skip]
+    pub const ALL: [Letter; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M,
+        N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+
+    // position in the alphabet, starting from 0
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
     pub fn from_keycode(code: KeyCode) -> Option<Self> {
         match code {
             KeyCode::KeyA => Some(A),
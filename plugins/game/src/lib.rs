@@ -1,21 +1,47 @@
 use serde::{Deserialize, Serialize};
 
-use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy::{core::FrameCount, ecs::entity::MapEntities, prelude::*};
 use bevy_replicon::prelude::*;
 
+mod account;
+pub use account::*;
 mod action;
 pub use action::*;
 mod arena;
 pub use arena::*;
+mod game_over;
+pub use game_over::*;
 mod letters;
 pub use letters::*;
+mod meter;
+pub use meter::*;
 mod player;
 pub use player::*;
+mod rating;
+pub use rating::*;
+mod replay;
+pub use replay::*;
+mod room;
+pub use room::*;
+mod rules;
+pub use rules::*;
+mod settings;
+pub use settings::*;
 mod wordlist;
 pub use wordlist::*;
 
 pub const PROTOCOL_ID: u64 = 1;
 
+// Handed out by the server's token endpoint. Clients need both parts to connect: the hash of the
+// server's self-signed certificate, and a signed netcode connect token carrying the client id
+// the server picked for them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+pub struct ServerToken {
+    pub cert_hash: Vec<u8>,
+    pub connect_token: Vec<u8>,
+}
+
 pub struct WordFightGamePlugin;
 
 impl Plugin for WordFightGamePlugin {
@@ -25,16 +51,34 @@ impl Plugin for WordFightGamePlugin {
             ..Default::default()
         }));
 
-        // TODO: perhaps we only want to include this on server.
-        // check whether it is currently "optimistic", if so, maybe we keep it
-        app.init_resource::<WordList>();
-        app.add_mapped_client_event::<ActionEvent>(ChannelKind::Ordered);
+        // only the server validates words, so clients built without the embedded
+        // word list simply start with no dictionaries
+        app.init_resource::<Dictionaries>();
+        app.add_event::<DictionaryLookup>();
+        app.add_client_event::<ActionEvent>(ChannelKind::Ordered);
+        app.add_server_event::<ActionRejected>(ChannelKind::Ordered);
+        app.add_server_event::<ActionFeedback>(ChannelKind::Ordered);
+        app.add_client_event::<RoomRequest>(ChannelKind::Ordered);
+        app.add_server_event::<RoomResponse>(ChannelKind::Ordered);
+        app.add_server_event::<Welcome>(ChannelKind::Ordered);
+        app.add_server_event::<ServerShutdown>(ChannelKind::Ordered);
+        app.add_event::<PlayerAction>();
+        app.add_event::<ActionProcessed>();
+        app.add_event::<StrikeEvent>();
+        app.add_event::<GameEnded>();
 
         app.add_systems(
             Update,
             (
                 Self::handle_input_actions,
+                Self::handle_player_actions,
                 Self::handle_word_contact,
+                Self::count_rounds,
+                Self::send_action_feedback,
+                Self::record_actions,
+                Self::record_strikes,
+                Self::check_game_over,
+                Self::save_replays.run_if(resource_exists::<ReplayDirectory>),
                 Self::despawn_empty_games,
                 Self::cleanup_game_entities,
             )
@@ -43,14 +87,29 @@ impl Plugin for WordFightGamePlugin {
         );
 
         app.observe(SpawnGame::observer);
+        app.init_resource::<ClientEntities>();
+        app.observe(ClientEntities::add_client);
+        app.observe(ClientEntities::remove_client);
 
         app.replicate::<Client>()
+            .replicate::<Bot>()
+            .replicate::<InRoom>()
             .replicate::<PlayerSide>()
             .replicate::<Word>()
             .replicate::<Score>()
+            .replicate::<Meter>()
+            .replicate::<Reach>()
             .replicate_mapped::<InGame>()
             .replicate::<Game>()
             .replicate::<Arena>()
+            .replicate::<StrikeRule>()
+            .replicate::<ValidationMode>()
+            .replicate::<WinCondition>()
+            .replicate::<DictionaryName>()
+            .replicate::<CompleteWordBonus>()
+            .replicate::<RoundsPlayed>()
+            .replicate::<LastStrike>()
+            .replicate::<GameResult>()
             .replicate_mapped::<GamePlayers>();
     }
 }
@@ -58,59 +117,144 @@ impl Plugin for WordFightGamePlugin {
 impl WordFightGamePlugin {
     fn handle_input_actions(
         mut action_events: EventReader<FromClient<ActionEvent>>,
-        mut players: Query<(&mut Word, &PlayerSide, &Client)>,
-        dictionary: Dictionary,
+        mut rejections: EventWriter<ToClients<ActionRejected>>,
+        mut player_actions: EventWriter<PlayerAction>,
+        clients: Res<ClientEntities>,
     ) {
-        for FromClient {
-            client_id,
-            event: action,
-        } in action_events.read()
-        {
+        for FromClient { client_id, event } in action_events.read() {
+            let action = event.action;
             info!("Client {client_id:?} took action: {action:?}");
-            let ActionEvent {
-                action,
-                side,
-                actor,
-            } = action;
-            let Ok((mut word, player_side, client)) = players.get_mut(*actor) else {
+
+            // the sender is only ever identified by its connection, never by anything it sends
+            let Some(&player) = clients.get(client_id) else {
+                warn!("Client {client_id:?} has no player entity");
+                rejections.send(ToClients {
+                    mode: SendMode::Direct(*client_id),
+                    event: ActionRejected {
+                        action,
+                        reason: RejectionReason::UnknownPlayer,
+                    },
+                });
                 continue;
             };
+            player_actions.send(PlayerAction { player, action });
+        }
+    }
 
-            if **client == *client_id && *player_side == *side {
-                info!("Action {action:?} applied to \"{}\"", word.clone());
-                action.apply(&mut word, &dictionary);
+    fn handle_player_actions(
+        mut player_actions: EventReader<PlayerAction>,
+        mut rejections: EventWriter<ToClients<ActionRejected>>,
+        mut processed: EventWriter<ActionProcessed>,
+        clients: Query<&Client>,
+        mut players: Query<(&mut Word, &mut Meter, &mut Reach, &PlayerSide, &InGame)>,
+        games: Query<(
+            &Arena,
+            &GamePlayers,
+            &DictionaryName,
+            &ValidationMode,
+            Has<GameResult>,
+        )>,
+        mut dictionary: Dictionary,
+    ) {
+        for PlayerAction { player, action } in player_actions.read() {
+            let (player, action) = (*player, *action);
+            // only players controlled by a client have anyone to tell about a rejection
+            let rejection = |reason| {
+                clients.get(player).ok().map(|client| ToClients {
+                    mode: SendMode::Direct(**client),
+                    event: ActionRejected { action, reason },
+                })
+            };
+            let Ok((_, _, _, side, in_game)) = players.get(player) else {
+                rejections.send_batch(rejection(RejectionReason::NotInGame));
+                continue;
+            };
+            let (side, game) = (*side, **in_game);
+            let Ok((arena, game_players, dictionary_name, mode, is_finished)) = games.get(game)
+            else {
+                rejections.send_batch(rejection(RejectionReason::NotInGame));
+                continue;
+            };
+            if is_finished {
+                info!("Ignoring action for finished game {game:?}");
+                rejections.send_batch(rejection(RejectionReason::GameOver));
+                continue;
             }
+            let opponent = match side {
+                PlayerSide::Left => game_players.right,
+                PlayerSide::Right => game_players.left,
+            };
+            let Ok([(mut word, mut meter, mut reach, ..), (mut opponent_word, ..)]) =
+                players.get_many_mut([player, opponent])
+            else {
+                error!("Game {game:?}: Failed to find players {game_players:?}");
+                continue;
+            };
+
+            info!("Action {action:?} applied to \"{}\"", word.clone());
+            let outcome = if action.is_special() {
+                action.apply_special(&word, &mut meter, &mut reach, &mut opponent_word, arena)
+            } else {
+                dictionary.lookup(dictionary_name, |words| {
+                    action.apply(&mut word, arena, words, *mode)
+                })
+            };
+            processed.send(ActionProcessed {
+                game,
+                player,
+                side,
+                action,
+                outcome,
+                word: word.clone(),
+            });
         }
     }
 
     fn handle_word_contact(
-        mut players: Query<(&mut Word, &mut Score)>,
-        games: Query<(Entity, &Arena, &GamePlayers)>,
+        mut players: Query<(&mut Word, &mut Score, &mut Meter, &mut Reach)>,
+        games: Query<(Entity, &Arena, &GamePlayers, &DictionaryName), Without<GameResult>>,
+        rules: Query<(&StrikeRule, &CompleteWordBonus)>,
+        mut strikes: EventWriter<StrikeEvent>,
+        dictionary: Dictionary,
     ) {
-        for (game, arena, game_players) in &games {
-            let Ok([(left_word, _), (right_word, _)]) =
+        for (game, arena, game_players, dictionary_name) in &games {
+            let Ok((strike_rule, complete_word_bonus)) = rules.get(game) else {
+                continue;
+            };
+            let words = dictionary.get(dictionary_name);
+            let Ok([(left_word, _, _, left_reach), (right_word, _, _, right_reach)]) =
                 players.get_many([game_players.left, game_players.right])
             else {
                 error!("Game {game:?}: Failed to find players {game_players:?}");
                 continue;
             };
-            let Ok(strike) = arena.strike(left_word, right_word) else {
+            let Ok(strike) = arena.strike_with_reach(
+                left_word,
+                right_word,
+                **left_reach + **right_reach,
+                strike_rule,
+                words,
+            ) else {
                 continue;
             };
+            let is_complete = |word: &Word| words.is_word(&word.to_string().to_lowercase());
+            let strike =
+                complete_word_bonus.apply(strike, is_complete(left_word), is_complete(right_word));
             info!("Game {game:?}: Strike occurred: {strike:?}");
+            strikes.send(StrikeEvent { game, strike });
             // contact has occurred!
             // first determine whether anyone gets a point
             match strike {
-                Strike::Score(winning_side) => {
+                Strike::Score(winning_side, _) => {
                     let winner = match winning_side {
                         PlayerSide::Left => game_players.left,
                         PlayerSide::Right => game_players.right,
                     };
-                    let Ok((_, mut score)) = players.get_mut(winner) else {
+                    let Ok((_, mut score, ..)) = players.get_mut(winner) else {
                         error!("Game {game:?}: Failed to find winner! {winner}");
                         continue;
                     };
-                    **score += 1;
+                    **score += strike.points();
                     info!(
                         "Game {game:?}: Player {winner:?} (side {winning_side:?} gains score! Total: {}",
                         **score
@@ -119,21 +263,143 @@ impl WordFightGamePlugin {
                 // both parry conditions result in no score change
                 Strike::OverRange | Strike::Parry => {}
             }
-            // then clear both player words
-            for (mut word, _) in players
-                .get_many_mut([game_players.left, game_players.right])
-                .into_iter()
-                .flatten()
+            // then fill meters and clear both player words
+            for (side, (mut word, _, mut meter, mut reach)) in
+                [PlayerSide::Left, PlayerSide::Right].into_iter().zip(
+                    players
+                        .get_many_mut([game_players.left, game_players.right])
+                        .into_iter()
+                        .flatten(),
+                )
             {
+                meter.fill(Meter::strike_reward(&word, strike.winner() == Some(side)));
                 word.clear();
+                **reach = 0;
+            }
+        }
+    }
+
+    fn count_rounds(
+        mut strikes: EventReader<StrikeEvent>,
+        mut games: Query<(&mut RoundsPlayed, &mut LastStrike)>,
+    ) {
+        for StrikeEvent { game, strike } in strikes.read() {
+            if let Ok((mut rounds, mut last_strike)) = games.get_mut(*game) {
+                **rounds += 1;
+                *last_strike = LastStrike::new(*strike);
+            }
+        }
+    }
+
+    fn send_action_feedback(
+        mut processed: EventReader<ActionProcessed>,
+        mut feedback: EventWriter<ToClients<ActionFeedback>>,
+        clients: Query<&Client>,
+    ) {
+        for ActionProcessed {
+            player,
+            action,
+            outcome,
+            ..
+        } in processed.read()
+        {
+            let Ok(client) = clients.get(*player) else {
+                continue;
+            };
+            feedback.send(ToClients {
+                mode: SendMode::Direct(**client),
+                event: ActionFeedback {
+                    action: *action,
+                    outcome: *outcome,
+                },
+            });
+        }
+    }
+
+    // actions always happen before any strike they cause, so record them first
+    fn record_actions(
+        mut processed: EventReader<ActionProcessed>,
+        mut logs: Query<&mut GameLog>,
+        frame: Res<FrameCount>,
+    ) {
+        for event in processed.read().filter(|event| event.outcome.is_accepted()) {
+            if let Ok(mut log) = logs.get_mut(event.game) {
+                log.record_action(frame.0, event.side, event.action, &event.word);
+            }
+        }
+    }
+
+    fn record_strikes(
+        mut strikes: EventReader<StrikeEvent>,
+        mut logs: Query<&mut GameLog>,
+        frame: Res<FrameCount>,
+    ) {
+        for StrikeEvent { game, strike } in strikes.read() {
+            if let Ok(mut log) = logs.get_mut(*game) {
+                log.record_strike(frame.0, *strike);
             }
         }
     }
 
+    fn check_game_over(
+        mut commands: Commands,
+        mut game_ended: EventWriter<GameEnded>,
+        games: Query<
+            (
+                Entity,
+                &GamePlayers,
+                &WinCondition,
+                &CompleteWordBonus,
+                &RoundsPlayed,
+            ),
+            Without<GameResult>,
+        >,
+        scores: Query<&Score>,
+    ) {
+        for (game, game_players, win_condition, bonus, rounds) in &games {
+            let Ok([left_score, right_score]) =
+                scores.get_many([game_players.left, game_players.right])
+            else {
+                error!("Game {game:?}: Failed to find players {game_players:?}");
+                continue;
+            };
+            let Some(result) = win_condition.result(**rounds, **left_score, **right_score, *bonus)
+            else {
+                continue;
+            };
+            info!("Game {game:?}: Game over! {result:?}");
+            commands.entity(game).insert(result);
+            game_ended.send(GameEnded { game, result });
+        }
+    }
+
+    fn save_replays(
+        mut game_ended: EventReader<GameEnded>,
+        games: Query<(&Arena, &GameLog, GameSettingsQuery)>,
+        directory: Res<ReplayDirectory>,
+    ) {
+        for GameEnded { game, result } in game_ended.read() {
+            let Ok((arena, log, settings)) = games.get(*game) else {
+                continue;
+            };
+            let replay = Replay::new(arena, log, *result).with_settings(settings.settings());
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let path = directory.join(format!("replay-{timestamp}-{}.json", game.index()));
+            match replay.save(&path) {
+                Ok(()) => info!("Game {game:?}: Saved replay to {}", path.display()),
+                Err(error) => error!("Game {game:?}: Failed to save replay: {error}"),
+            }
+        }
+    }
+
+    // players are not always clients (e.g. bots), so count anything that has taken a side
     fn despawn_empty_games(
         mut commands: Commands,
         games: Query<Entity, With<Game>>,
-        players: Query<&InGame, With<Client>>,
+        players: Query<&InGame, With<PlayerSide>>,
     ) {
         for game in games.iter() {
             match players.iter().filter(|in_game| in_game.0 == game).count() {
@@ -150,18 +416,24 @@ impl WordFightGamePlugin {
         }
     }
 
+    // clients outlive their games, so they are only taken out of them
     fn cleanup_game_entities(
         mut commands: Commands,
         mut removed_games: RemovedComponents<Game>,
-        game_entities: Query<(Entity, &InGame)>,
+        game_entities: Query<(Entity, &InGame, Has<Client>)>,
     ) {
         for game in removed_games.read() {
-            for (entity, _) in game_entities
+            for (entity, _, is_client) in game_entities
                 .iter()
-                .filter(|(_, in_game)| in_game.0 == game)
+                .filter(|(_, in_game, _)| in_game.0 == game)
             {
-                info!("Game {game:?}: Cleaning up entity {entity:?}");
-                commands.entity(entity).despawn();
+                if is_client {
+                    info!("Game {game:?}: Returning client {entity:?} to the lobby");
+                    commands.entity(entity).remove::<PlayerBundle>();
+                } else {
+                    info!("Game {game:?}: Cleaning up entity {entity:?}");
+                    commands.entity(entity).despawn();
+                }
             }
         }
     }
@@ -171,31 +443,94 @@ impl WordFightGamePlugin {
 #[derive(SystemSet)]
 pub struct WordFightSystems;
 
-impl Action {
-    pub fn made_by(self, entity: Entity, side: PlayerSide) -> ActionEvent {
-        ActionEvent {
-            action: self,
-            side,
-            actor: entity,
-        }
-    }
-}
-
+// Sent by clients. The server works out which player sent it from the connection itself.
 #[derive(Clone, Copy, Debug)]
 #[derive(Event, Reflect)]
 #[derive(Serialize, Deserialize)]
 pub struct ActionEvent {
     action: Action,
-    side: PlayerSide,
-    actor: Entity,
 }
 
-impl MapEntities for ActionEvent {
-    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
-        self.actor = mapper.map_entity(self.actor);
+impl From<Action> for ActionEvent {
+    fn from(action: Action) -> Self {
+        ActionEvent { action }
     }
 }
 
+impl ActionEvent {
+    pub fn action(&self) -> Action {
+        self.action
+    }
+}
+
+// Server-side request for a player to act. Client ActionEvents are resolved into these,
+// and anything else that controls a player (e.g. bots) sends them directly.
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+pub struct PlayerAction {
+    pub player: Entity,
+    pub action: Action,
+}
+
+// Sent to a client when its action could not be attributed to a player in a running game
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+#[derive(Serialize, Deserialize)]
+pub struct ActionRejected {
+    pub action: Action,
+    pub reason: RejectionReason,
+}
+
+// Sent to the acting client for every action its player takes, accepted or not
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+#[derive(Serialize, Deserialize)]
+pub struct ActionFeedback {
+    pub action: Action,
+    pub outcome: ActionOutcome,
+}
+
+// Broadcast once the server starts shutting down. Games being played may still finish,
+// for up to `deadline_secs`, but no new ones start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Event)]
+#[derive(Serialize, Deserialize)]
+pub struct ServerShutdown {
+    pub deadline_secs: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub enum RejectionReason {
+    // no player entity belongs to the sending client
+    UnknownPlayer,
+    // the sending client is not currently in a game
+    NotInGame,
+    // the sending client's game has already ended
+    GameOver,
+}
+
+// Sent for every action taken by a player in a running game, accepted or not
+#[derive(Clone, Debug)]
+#[derive(Event)]
+pub struct ActionProcessed {
+    pub game: Entity,
+    pub player: Entity,
+    pub side: PlayerSide,
+    pub action: Action,
+    pub outcome: ActionOutcome,
+    // the player's word right after the action was applied
+    pub word: Word,
+}
+
+// Sent whenever two words strike each other, in addition to the score updates
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+pub struct StrikeEvent {
+    pub game: Entity,
+    pub strike: Strike,
+}
+
 #[derive(Clone, Debug)]
 #[derive(Component, Deref, DerefMut, Reflect)]
 #[derive(Deserialize, Serialize)]
@@ -213,6 +548,7 @@ pub struct SpawnGame {
     arena_size: usize,
     client1: Entity,
     client2: Entity,
+    settings: GameSettings,
 }
 
 impl SpawnGame {
@@ -221,38 +557,66 @@ impl SpawnGame {
             arena_size,
             client1,
             client2,
+            settings: GameSettings::default(),
         }
     }
 
-    fn observer(trigger: Trigger<Self>, mut commands: Commands) {
+    pub fn with_settings(mut self, settings: GameSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    fn observer(
+        trigger: Trigger<Self>,
+        mut commands: Commands,
+        dictionaries: Res<Dictionaries>,
+        frame: Res<FrameCount>,
+    ) {
         let player_one = trigger.event().client1;
         let player_two = trigger.event().client2;
+        let mut settings = trigger.event().settings.clone();
+        if !dictionaries.contains(&settings.dictionary) {
+            warn!(
+                "Unknown dictionary {}, falling back to {DEFAULT_DICTIONARY}",
+                settings.dictionary
+            );
+            settings.dictionary = DictionaryName::default();
+        }
+        if settings.validation_mode == ValidationMode::Infix {
+            // build the infix index now rather than on the first keystroke of the game
+            if let Some(words) = dictionaries.get(&settings.dictionary) {
+                words.is_infix("");
+            }
+        }
         let game = commands
             .spawn((
-                GameBundle::new(player_one, player_two, trigger.event().arena_size),
+                GameBundle::new(player_one, player_two, trigger.event().arena_size)
+                    .with_settings(settings.clone()),
+                GameLog::new(frame.0),
                 Replicated,
             ))
             .id();
-        commands.entity(player_one).insert(PlayerBundle {
-            side: PlayerSide::Left,
-            word: Word::default(),
-            score: Score::default(),
-            in_game: InGame(game),
-        });
-        commands.entity(player_two).insert(PlayerBundle {
-            side: PlayerSide::Right,
-            word: Word::default(),
-            score: Score::default(),
-            in_game: InGame(game),
-        });
-        info!("Spawned game {game} with players {player_one}, {player_two}");
+        commands
+            .entity(player_one)
+            .insert(PlayerBundle::new(PlayerSide::Left, game));
+        commands
+            .entity(player_two)
+            .insert(PlayerBundle::new(PlayerSide::Right, game));
+        info!(
+            "Spawned game {game} ({} rule, {} mode, {}, {} dictionary, complete word bonus: {}) with players {player_one}, {player_two}",
+            settings.strike_rule,
+            settings.validation_mode,
+            settings.win_condition,
+            settings.dictionary,
+            settings.complete_word_bonus,
+        );
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bevy::MinimalPlugins;
+    use bevy::{ecs::event::ManualEventReader, MinimalPlugins};
 
     const ALPHABET: [Letter; 8] = [
         Letter::A,
@@ -265,6 +629,9 @@ mod tests {
         Letter::T,
     ];
 
+    const LEFT_CLIENT: ClientId = ClientId::new(1);
+    const RIGHT_CLIENT: ClientId = ClientId::new(2);
+
     fn app() -> App {
         let mut app = App::new();
         app.add_plugins(MinimalPlugins);
@@ -292,6 +659,13 @@ mod tests {
         (player_one, player_two)
     }
 
+    fn send_action(world: &mut World, client_id: ClientId, action: Action) {
+        world.send_event(FromClient {
+            client_id,
+            event: ActionEvent::from(action),
+        });
+    }
+
     fn set_word(world: &mut World, player: Entity, new_word: Vec<Letter>) {
         let mut word = world.get_mut::<Word>(player).unwrap();
         **word = new_word;
@@ -319,11 +693,11 @@ mod tests {
         let size = 7;
         let client1 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(LEFT_CLIENT).bundle())
             .id();
         let client2 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
             .id();
         app.world_mut()
             .trigger(SpawnGame::new(size, client1, client2));
@@ -345,10 +719,8 @@ mod tests {
         let score = app.world().get::<Score>(player_two).unwrap();
         assert_eq!(**score, 0);
 
-        app.world_mut().send_event::<ActionEvent>(
-            Action::Append(ALPHABET[3]).made_by(player_one, PlayerSide::Left),
-        );
-        // update twice to process the event through replicon
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(ALPHABET[3]));
+        // update twice to let the action and any resulting strike settle
         app.update();
         app.update();
 
@@ -364,11 +736,11 @@ mod tests {
 
         let client1 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(LEFT_CLIENT).bundle())
             .id();
         let client2 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
             .id();
         app.world_mut()
             .trigger(SpawnGame::new(size, client1, client2));
@@ -386,10 +758,8 @@ mod tests {
         assert_word_sizes(app.world(), (player_one, 6), (player_two, 0));
         assert_scores(app.world(), (player_one, 0), (player_two, 0));
 
-        app.world_mut().send_event::<ActionEvent>(
-            Action::Append(ALPHABET[6]).made_by(player_one, PlayerSide::Left),
-        );
-        // update twice to process the event through replicon
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(ALPHABET[6]));
+        // update twice to let the action and any resulting strike settle
         app.update();
         app.update();
 
@@ -405,11 +775,11 @@ mod tests {
 
         let client1 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(LEFT_CLIENT).bundle())
             .id();
         let client2 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
             .id();
         app.world_mut()
             .trigger(SpawnGame::new(size, client1, client2));
@@ -424,9 +794,7 @@ mod tests {
             assert_word_sizes(app.world(), (player_one, index), (player_two, index));
             assert_scores(app.world(), (player_one, 0), (player_two, 0));
 
-            app.world_mut().send_event::<ActionEvent>(
-                Action::Append(*letter).made_by(player_one, PlayerSide::Left),
-            );
+            send_action(app.world_mut(), LEFT_CLIENT, Action::Append(*letter));
             app.update();
             app.update();
 
@@ -434,9 +802,7 @@ mod tests {
             assert_word_sizes(app.world(), (player_one, index + 1), (player_two, index));
             assert_scores(app.world(), (player_one, 0), (player_two, 0));
 
-            app.world_mut().send_event::<ActionEvent>(
-                Action::Append(*letter).made_by(player_two, PlayerSide::Right),
-            );
+            send_action(app.world_mut(), RIGHT_CLIENT, Action::Append(*letter));
             app.update();
             app.update();
         }
@@ -444,15 +810,406 @@ mod tests {
         assert_word_sizes(app.world(), (player_one, 3), (player_two, 3));
         assert_scores(app.world(), (player_one, 0), (player_two, 0));
 
-        app.world_mut().send_event::<ActionEvent>(
-            Action::Append(ALPHABET[3]).made_by(player_two, PlayerSide::Right),
+        send_action(app.world_mut(), RIGHT_CLIENT, Action::Append(ALPHABET[3]));
+        // update twice to let the action and any resulting strike settle
+        app.update();
+        app.update();
+
+        assert_word_sizes(app.world(), (player_one, 0), (player_two, 0));
+        assert_scores(app.world(), (player_one, 1), (player_two, 0));
+    }
+
+    // test that the game's StrikeRule decides the winner instead of plain alphabet order
+    #[test]
+    fn test_strike_rule_reverse_alphabetical() {
+        let mut app = app();
+
+        let size = 7;
+        let client1 = app
+            .world_mut()
+            .spawn(Client::from(LEFT_CLIENT).bundle())
+            .id();
+        let client2 = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut()
+            .trigger(SpawnGame::new(size, client1, client2).with_settings(
+                GameSettings::default().with_strike_rule(StrikeRule::ReverseAlphabetical),
+            ));
+        // update to let spawns / etc flush
+        app.update();
+
+        let (player_one, player_two) = find_players(app.world_mut());
+
+        let first_three_letters: Vec<Letter> = ALPHABET[0..3].to_vec();
+        set_word(app.world_mut(), player_one, first_three_letters.clone());
+        set_word(app.world_mut(), player_two, first_three_letters);
+
+        // nothing should happen here, but update to prevent influencing tests of future mutations
+        app.update();
+
+        assert_word_sizes(app.world(), (player_one, 3), (player_two, 3));
+        assert_scores(app.world(), (player_one, 0), (player_two, 0));
+
+        // P beats H alphabetically, but H wins when the order is reversed
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(ALPHABET[3]));
+        // update twice to let the action and any resulting strike settle
+        app.update();
+        app.update();
+
+        assert_word_sizes(app.world(), (player_one, 0), (player_two, 0));
+        assert_scores(app.world(), (player_one, 1), (player_two, 0));
+    }
+
+    // test that reaching the WinCondition ends the game and stops accepting actions
+    #[test]
+    fn test_game_over() {
+        let mut app = app();
+        let size = 7;
+
+        let client1 = app
+            .world_mut()
+            .spawn(Client::from(LEFT_CLIENT).bundle())
+            .id();
+        let client2 = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut()
+            .trigger(SpawnGame::new(size, client1, client2).with_settings(
+                GameSettings::default().with_win_condition(WinCondition::Points(1)),
+            ));
+        // update to let spawns / etc flush
+        app.update();
+
+        let (player_one, player_two) = find_players(app.world_mut());
+        let game = **app.world().get::<InGame>(player_one).unwrap();
+
+        let first_six_letters = ALPHABET[0..6].to_vec();
+        set_word(app.world_mut(), player_one, first_six_letters);
+        app.update();
+        assert!(app.world().get::<GameResult>(game).is_none());
+
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(ALPHABET[6]));
+        // update twice to let the action and any resulting strike settle
+        app.update();
+        app.update();
+
+        assert_scores(app.world(), (player_one, 1), (player_two, 0));
+        let result = app.world().get::<GameResult>(game).unwrap();
+        assert_eq!(result.winner, Some(PlayerSide::Left));
+        assert_eq!((result.left_score, result.right_score), (1, 0));
+        let ended = app.world().resource::<Events<GameEnded>>();
+        assert_eq!(ended.get_reader().read(ended).count(), 1);
+
+        // actions after the game has ended are ignored
+        send_action(app.world_mut(), RIGHT_CLIENT, Action::Append(ALPHABET[0]));
+        app.update();
+        app.update();
+        assert_word_sizes(app.world(), (player_one, 0), (player_two, 0));
+    }
+
+    // test that actions from clients without a player are rejected back to the sender
+    #[test]
+    fn test_action_rejected_unknown_player() {
+        let mut app = app();
+
+        send_action(app.world_mut(), ClientId::SERVER, Action::Append(Letter::A));
+        // update twice to receive the rejection through replicon
+        app.update();
+        app.update();
+
+        let rejections = app.world().resource::<Events<ActionRejected>>();
+        let rejections = rejections
+            .get_reader()
+            .read(rejections)
+            .map(|rejection| rejection.reason)
+            .collect::<Vec<_>>();
+        assert_eq!(rejections, vec![RejectionReason::UnknownPlayer]);
+    }
+
+    // test that every action is answered with its outcome
+    #[test]
+    fn test_action_feedback() {
+        let mut app = app();
+        let size = 7;
+
+        // the server's own client id receives its feedback locally
+        let client1 = app
+            .world_mut()
+            .spawn(Client::from(ClientId::SERVER).bundle())
+            .id();
+        let client2 = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut()
+            .trigger(SpawnGame::new(size, client1, client2));
+        app.update();
+
+        let (player_one, _) = find_players(app.world_mut());
+        set_word(app.world_mut(), player_one, ALPHABET[0..4].to_vec());
+        app.update();
+
+        // feedback only lasts a couple of frames, so it is read after every update
+        let mut feedback = ManualEventReader::<ActionFeedback>::default();
+        let mut outcomes = vec![];
+        let mut update = |app: &mut App| {
+            app.update();
+            let events = app.world().resource::<Events<ActionFeedback>>();
+            outcomes.extend(feedback.read(events).map(|feedback| feedback.outcome));
+        };
+
+        send_action(app.world_mut(), ClientId::SERVER, Action::Append(Letter::Q));
+        send_action(app.world_mut(), ClientId::SERVER, Action::Append(Letter::A));
+        update(&mut app);
+
+        set_word(app.world_mut(), player_one, vec![]);
+        send_action(app.world_mut(), ClientId::SERVER, Action::Delete);
+        update(&mut app);
+
+        assert_eq!(
+            outcomes,
+            vec![
+                ActionOutcome::NotAPrefix,
+                ActionOutcome::Accepted,
+                ActionOutcome::WordEmpty
+            ]
         );
-        // update twice to process the event through replicon
+    }
+
+    fn set_meter(world: &mut World, player: Entity, amount: usize) {
+        *world.get_mut::<Meter>(player).unwrap() = Meter::new(amount);
+    }
+
+    fn spawn_game(app: &mut App, size: usize) -> (Entity, Entity) {
+        let client1 = app
+            .world_mut()
+            .spawn(Client::from(LEFT_CLIENT).bundle())
+            .id();
+        let client2 = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut()
+            .trigger(SpawnGame::new(size, client1, client2));
+        // update to let spawns / etc flush
         app.update();
+        find_players(app.world_mut())
+    }
+
+    // test that each game validates words against the dictionary it was spawned with
+    #[test]
+    fn test_per_game_dictionary() {
+        let mut app = app();
+        app.world_mut()
+            .resource_mut::<Dictionaries>()
+            .insert("zoo", WordList::new(["zebra"]));
+
+        let client1 = app
+            .world_mut()
+            .spawn(Client::from(LEFT_CLIENT).bundle())
+            .id();
+        let client2 = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut()
+            .trigger(SpawnGame::new(7, client1, client2).with_settings(
+                GameSettings::default().with_dictionary(DictionaryName::new("zoo")),
+            ));
         app.update();
+        let (player_one, player_two) = find_players(app.world_mut());
+
+        // "a" is a word in the default dictionary, but not in this one
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(Letter::A));
+        send_action(app.world_mut(), RIGHT_CLIENT, Action::Append(Letter::Z));
+        app.update();
+        app.update();
+
+        assert_word_sizes(app.world(), (player_one, 0), (player_two, 1));
+    }
 
+    // test that infix games accept letters that continue any part of a word
+    #[test]
+    fn test_validation_mode_infix() {
+        let mut app = app();
+        let client1 = app
+            .world_mut()
+            .spawn(Client::from(LEFT_CLIENT).bundle())
+            .id();
+        let client2 = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut()
+            .trigger(SpawnGame::new(7, client1, client2).with_settings(
+                GameSettings::default().with_validation_mode(ValidationMode::Infix),
+            ));
+        app.update();
+        let (player_one, player_two) = find_players(app.world_mut());
+
+        // "ltr" only appears in the middle of "paltry"
+        for letter in [Letter::L, Letter::T, Letter::R] {
+            send_action(app.world_mut(), LEFT_CLIENT, Action::Append(letter));
+            app.update();
+        }
+        app.update();
+
+        assert_word_sizes(app.world(), (player_one, 3), (player_two, 0));
+    }
+
+    // test that complete words score an extra point and break ties
+    #[test]
+    fn test_complete_word_bonus() {
+        let mut app = app();
+        app.world_mut().resource_mut::<Dictionaries>().insert(
+            "bonus",
+            WordList::new(["alpha", "alphabet", "pat", "patronize"]),
+        );
+
+        let client1 = app
+            .world_mut()
+            .spawn(Client::from(LEFT_CLIENT).bundle())
+            .id();
+        let client2 = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut().trigger(
+            SpawnGame::new(7, client1, client2).with_settings(
+                GameSettings::default()
+                    .with_dictionary(DictionaryName::new("bonus"))
+                    .with_complete_word_bonus(CompleteWordBonus::ExtraPoint),
+            ),
+        );
+        app.update();
+        let (player_one, player_two) = find_players(app.world_mut());
+        let game = **app.world().get::<InGame>(player_one).unwrap();
+
+        // "pat" wins on its letters (T > H), and is a complete word
+        set_word(app.world_mut(), player_one, vec![Letter::P, Letter::A]);
+        set_word(app.world_mut(), player_two, ALPHABET[0..4].to_vec());
+        app.update();
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(Letter::T));
+        app.update();
+        app.update();
+
+        assert_scores(app.world(), (player_one, 2), (player_two, 0));
+        assert_eq!(
+            **app.world().get::<LastStrike>(game).unwrap(),
+            Some(Strike::Score(
+                PlayerSide::Left,
+                Some(StrikeBonus::CompleteWord)
+            ))
+        );
+
+        // "alpha" ties with "pa" on its letters, but only "alpha" is a complete word
+        set_word(app.world_mut(), player_one, vec![Letter::P, Letter::A]);
+        set_word(app.world_mut(), player_two, ALPHABET[0..4].to_vec());
+        app.update();
+        send_action(app.world_mut(), RIGHT_CLIENT, Action::Append(Letter::A));
+        app.update();
+        app.update();
+
+        assert_scores(app.world(), (player_one, 2), (player_two, 1));
+        assert_eq!(
+            **app.world().get::<LastStrike>(game).unwrap(),
+            Some(Strike::Score(
+                PlayerSide::Right,
+                Some(StrikeBonus::TieBreak)
+            ))
+        );
+    }
+
+    // test that scoring strikes and long words fill the meter
+    #[test]
+    fn test_meter_fills_from_strikes() {
+        let mut app = app();
+        let (player_one, player_two) = spawn_game(&mut app, 7);
+
+        set_word(app.world_mut(), player_one, ALPHABET[0..6].to_vec());
+        app.update();
+
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(ALPHABET[6]));
+        // update twice to let the action and any resulting strike settle
+        app.update();
+        app.update();
+
+        assert_scores(app.world(), (player_one, 1), (player_two, 0));
+        // one for scoring and one for striking with a long word
+        assert_eq!(**app.world().get::<Meter>(player_one).unwrap(), 2);
+        assert_eq!(**app.world().get::<Meter>(player_two).unwrap(), 0);
+    }
+
+    // test that SuperCollapse shortens the opponent's word and is paid for from the meter
+    #[test]
+    fn test_super_collapse() {
+        let mut app = app();
+        let (player_one, player_two) = spawn_game(&mut app, 7);
+
+        set_word(app.world_mut(), player_two, ALPHABET[0..4].to_vec());
+        set_meter(app.world_mut(), player_one, Action::SuperCollapse.cost());
+        app.update();
+
+        send_action(app.world_mut(), LEFT_CLIENT, Action::SuperCollapse);
+        app.update();
+        app.update();
+
+        assert_word_sizes(app.world(), (player_one, 0), (player_two, 1));
+        assert_eq!(**app.world().get::<Meter>(player_one).unwrap(), 0);
+
+        // the meter is empty now, so nothing else happens
+        send_action(app.world_mut(), LEFT_CLIENT, Action::SuperCollapse);
+        app.update();
+        app.update();
+
+        assert_word_sizes(app.world(), (player_one, 0), (player_two, 1));
+    }
+
+    // test that SuperExtend reaches across a few empty slots, forcing a strike once they close the gap
+    #[test]
+    fn test_super_extend() {
+        let mut app = app();
+        let (player_one, player_two) = spawn_game(&mut app, 7);
+
+        set_word(app.world_mut(), player_one, ALPHABET[0..3].to_vec());
+        set_word(app.world_mut(), player_two, ALPHABET[0..2].to_vec());
+        app.update();
+
+        // without enough meter the action is refused and nothing moves
+        send_action(app.world_mut(), LEFT_CLIENT, Action::SuperExtend);
+        app.update();
+        app.update();
+        assert_word_sizes(app.world(), (player_one, 3), (player_two, 2));
+        assert_eq!(**app.world().get::<Reach>(player_one).unwrap(), 0);
+
+        set_meter(app.world_mut(), player_one, Action::SuperExtend.cost());
+        send_action(app.world_mut(), LEFT_CLIENT, Action::SuperExtend);
+        app.update();
+        app.update();
+
+        // P strikes L and scores, then the reach is used up
         assert_word_sizes(app.world(), (player_one, 0), (player_two, 0));
         assert_scores(app.world(), (player_one, 1), (player_two, 0));
+        assert_eq!(**app.world().get::<Reach>(player_one).unwrap(), 0);
+        // the full cost was spent before the strike refilled one for scoring
+        assert_eq!(**app.world().get::<Meter>(player_one).unwrap(), 1);
+
+        // a wider gap is only partly closed, so no strike happens yet
+        set_word(app.world_mut(), player_one, ALPHABET[0..1].to_vec());
+        set_word(app.world_mut(), player_two, ALPHABET[0..1].to_vec());
+        set_meter(app.world_mut(), player_one, Action::SuperExtend.cost());
+        send_action(app.world_mut(), LEFT_CLIENT, Action::SuperExtend);
+        app.update();
+        app.update();
+        assert_word_sizes(app.world(), (player_one, 1), (player_two, 1));
+        assert_eq!(
+            **app.world().get::<Reach>(player_one).unwrap(),
+            EXTEND_SLOTS
+        );
+        assert_eq!(**app.world().get::<Meter>(player_one).unwrap(), 0);
     }
 
     // test the Strike::Parry behavior
@@ -464,11 +1221,11 @@ mod tests {
 
         let client1 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(LEFT_CLIENT).bundle())
             .id();
         let client2 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
             .id();
         app.world_mut()
             .trigger(SpawnGame::new(size, client1, client2));
@@ -488,10 +1245,8 @@ mod tests {
         assert_scores(app.world(), (player_one, 0), (player_two, 0));
 
         // send the 3rd letter again to create a tie, resulting in a Strike::Parry
-        app.world_mut().send_event::<ActionEvent>(
-            Action::Append(SASS[3]).made_by(player_one, PlayerSide::Left),
-        );
-        // update twice to process the event through replicon
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(SASS[3]));
+        // update twice to let the action and any resulting strike settle
         app.update();
         app.update();
 
@@ -507,10 +1262,8 @@ mod tests {
 
         assert_word_sizes(app.world(), (player_one, 3), (player_two, 3));
         assert_scores(app.world(), (player_one, 0), (player_two, 0));
-        app.world_mut().send_event::<ActionEvent>(
-            Action::Append(SASS[3]).made_by(player_two, PlayerSide::Right),
-        );
-        // update twice to process the event through replicon
+        send_action(app.world_mut(), RIGHT_CLIENT, Action::Append(SASS[3]));
+        // update twice to let the action and any resulting strike settle
         app.update();
         app.update();
 
@@ -526,11 +1279,11 @@ mod tests {
 
         let client1 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(LEFT_CLIENT).bundle())
             .id();
         let client2 = app
             .world_mut()
-            .spawn(Client::from(ClientId::SERVER).bundle())
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
             .id();
         app.world_mut()
             .trigger(SpawnGame::new(size, client1, client2));
@@ -550,13 +1303,9 @@ mod tests {
         assert_scores(app.world(), (player_one, 0), (player_two, 0));
 
         // two inputs at the same time!
-        app.world_mut().send_event::<ActionEvent>(
-            Action::Append(ALPHABET[3]).made_by(player_one, PlayerSide::Left),
-        );
-        app.world_mut().send_event::<ActionEvent>(
-            Action::Append(ALPHABET[3]).made_by(player_two, PlayerSide::Right),
-        );
-        // update twice to process the event through replicon
+        send_action(app.world_mut(), LEFT_CLIENT, Action::Append(ALPHABET[3]));
+        send_action(app.world_mut(), RIGHT_CLIENT, Action::Append(ALPHABET[3]));
+        // update twice to let the action and any resulting strike settle
         app.update();
         app.update();
 
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use bevy::prelude::*;
+
+use crate::Word;
+
+pub const METER_CAPACITY: usize = 4;
+// words at least this long when a strike lands fill the meter, whoever wins the strike
+pub const LONG_WORD_LENGTH: usize = 5;
+// how many letters SuperCollapse removes from the end of the opponent's word
+pub const COLLAPSE_LETTERS: usize = 3;
+// how many empty arena slots SuperExtend reaches across
+pub const EXTEND_SLOTS: usize = 2;
+
+// Filled by scoring strikes and by striking with long words, spent on special actions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Component, Deref, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct Meter(usize);
+
+impl Meter {
+    pub fn new(amount: usize) -> Self {
+        Meter(amount.min(METER_CAPACITY))
+    }
+
+    pub fn fill(&mut self, amount: usize) {
+        self.0 = (self.0 + amount).min(METER_CAPACITY);
+    }
+
+    // Returns whether the meter held enough to pay for the cost
+    pub fn spend(&mut self, cost: usize) -> bool {
+        if self.0 < cost {
+            return false;
+        }
+        self.0 -= cost;
+        true
+    }
+
+    // how much a player earns from a strike that just landed
+    pub fn strike_reward(word: &Word, scored: bool) -> usize {
+        usize::from(scored) + usize::from(word.len() >= LONG_WORD_LENGTH)
+    }
+}
+
+// Extra arena slots a player's word reaches across after SuperExtend.
+// Counts towards contact like letters do, and is reset by the next strike.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Component, Deref, DerefMut, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct Reach(usize);
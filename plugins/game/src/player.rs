@@ -1,9 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_replicon::prelude::*;
 
-use crate::{InGame, Letter};
+use crate::{InGame, Letter, Meter, Reach};
 
 #[derive(Debug)]
 #[derive(Component, Deref, Reflect)]
@@ -27,7 +27,44 @@ impl From<ClientId> for Client {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+// The player entity of every client, so that whatever a client sends can be traced back to
+// its player without going through all of them. Kept up to date as `Client`s come and go.
+#[derive(Debug, Default)]
+#[derive(Deref, Resource)]
+pub struct ClientEntities(HashMap<ClientId, Entity>);
+
+impl ClientEntities {
+    pub(crate) fn add_client(
+        trigger: Trigger<OnAdd, Client>,
+        clients: Query<&Client>,
+        mut entities: ResMut<ClientEntities>,
+    ) {
+        if let Ok(client) = clients.get(trigger.entity()) {
+            entities.0.insert(**client, trigger.entity());
+        }
+    }
+
+    pub(crate) fn remove_client(
+        trigger: Trigger<OnRemove, Client>,
+        clients: Query<&Client>,
+        mut entities: ResMut<ClientEntities>,
+    ) {
+        if let Ok(client) = clients.get(trigger.entity()) {
+            if entities.0.get(&**client) == Some(&trigger.entity()) {
+                entities.0.remove(&**client);
+            }
+        }
+    }
+}
+
+// Marks players that the server controls rather than a client, so that clients know
+// when they are playing against a bot.
+#[derive(Clone, Copy, Debug, Default)]
+#[derive(Component, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct Bot;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[derive(Component, Reflect)]
 #[derive(Deserialize, Serialize)]
 pub enum PlayerSide {
@@ -60,7 +97,7 @@ impl std::ops::Not for PlayerSide {
 #[derive(Deserialize, Serialize)]
 pub struct Score(usize);
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[derive(Component, Deref, DerefMut, Reflect)]
 #[derive(Deserialize, Serialize)]
 pub struct Word(Vec<Letter>);
@@ -84,5 +121,20 @@ pub struct PlayerBundle {
     pub side: PlayerSide,
     pub score: Score,
     pub word: Word,
+    pub meter: Meter,
+    pub reach: Reach,
     pub in_game: InGame,
 }
+
+impl PlayerBundle {
+    pub fn new(side: PlayerSide, game: Entity) -> Self {
+        PlayerBundle {
+            side,
+            score: Score::default(),
+            word: Word::default(),
+            meter: Meter::default(),
+            reach: Reach::default(),
+            in_game: InGame(game),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use bevy::prelude::*;
+
+use crate::{GameResult, PlayerSide};
+
+// where every player (or bot) starts out
+pub const DEFAULT_RATING: f64 = 1500.;
+// how far a single game can move a rating
+pub const RATING_K_FACTOR: f64 = 32.;
+
+// An Elo rating.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[derive(Component, Deref, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct Rating(f64);
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating(DEFAULT_RATING)
+    }
+}
+
+impl Rating {
+    pub fn new(rating: f64) -> Self {
+        Rating(rating)
+    }
+
+    // the score (1 for a win, 0.5 for a draw) this player is expected to get against `opponent`
+    pub fn expected_score(&self, opponent: Rating) -> f64 {
+        1. / (1. + 10f64.powf((opponent.0 - self.0) / 400.))
+    }
+
+    // moves this rating towards what its actual `score` against `opponent` suggests
+    pub fn update(&mut self, opponent: Rating, score: f64) {
+        self.0 += RATING_K_FACTOR * (score - self.expected_score(opponent));
+    }
+
+    // rates both players of a finished game
+    pub fn update_game(left: &mut Rating, right: &mut Rating, result: &GameResult) {
+        let left_score = match result.winner {
+            Some(PlayerSide::Left) => 1.,
+            Some(PlayerSide::Right) => 0.,
+            None => 0.5,
+        };
+        let (left_before, right_before) = (*left, *right);
+        left.update(right_before, left_score);
+        right.update(left_before, 1. - left_score);
+    }
+}
+
+impl std::fmt::Display for Rating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.0}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(winner: Option<PlayerSide>) -> GameResult {
+        GameResult {
+            winner,
+            left_score: 0,
+            right_score: 0,
+            aborted: false,
+            forfeit: false,
+        }
+    }
+
+    #[test]
+    fn test_ratings() {
+        let (strong, weak) = (Rating::new(1700.), Rating::new(1300.));
+        assert!(strong.expected_score(weak) > 0.9);
+        assert!((strong.expected_score(weak) + weak.expected_score(strong) - 1.).abs() < 1e-9);
+
+        // an even game between equals changes nothing
+        let (mut left, mut right) = (Rating::default(), Rating::default());
+        Rating::update_game(&mut left, &mut right, &result(None));
+        assert_eq!((left, right), (Rating::default(), Rating::default()));
+
+        // winners gain what losers lose, and upsets are worth more
+        let (mut left, mut right) = (weak, strong);
+        Rating::update_game(&mut left, &mut right, &result(Some(PlayerSide::Left)));
+        let upset = *left - *weak;
+        assert!((upset - (*strong - *right)).abs() < 1e-9);
+
+        let (mut left, mut right) = (strong, weak);
+        Rating::update_game(&mut left, &mut right, &result(Some(PlayerSide::Left)));
+        assert!(upset > *left - *strong);
+    }
+}
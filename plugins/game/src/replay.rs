@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+
+use crate::{
+    Action, ActionEvent, Arena, Client, Dictionaries, DictionaryName, GameResult, GameSettings,
+    InGame, PlayerSide, Score, SpawnGame, Strike, Word, WordFightGamePlugin,
+};
+
+// Bump this whenever the serialized shape of `Replay` changes.
+pub const REPLAY_VERSION: u32 = 1;
+
+// stand-in connections for the two players when replaying a game
+const LEFT_CLIENT: ClientId = ClientId::new(1);
+const RIGHT_CLIENT: ClientId = ClientId::new(2);
+
+// Server-side record of everything that happened in a game, in order.
+// Frames are counted from the frame the game was spawned on.
+#[derive(Clone, Debug, Default)]
+#[derive(Component)]
+pub struct GameLog {
+    start_frame: u32,
+    events: Vec<ReplayEvent>,
+}
+
+impl GameLog {
+    pub fn new(start_frame: u32) -> Self {
+        Self {
+            start_frame,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn events(&self) -> &[ReplayEvent] {
+        &self.events
+    }
+
+    pub fn record_action(&mut self, frame: u32, side: PlayerSide, action: Action, word: &Word) {
+        self.events.push(ReplayEvent::Action {
+            frame: frame.wrapping_sub(self.start_frame),
+            side,
+            action,
+            word: word.clone(),
+        });
+    }
+
+    pub fn record_strike(&mut self, frame: u32, strike: Strike) {
+        self.events.push(ReplayEvent::Strike {
+            frame: frame.wrapping_sub(self.start_frame),
+            strike,
+        });
+    }
+
+    pub fn strikes(&self) -> impl Iterator<Item = &Strike> {
+        self.events.iter().filter_map(|event| match event {
+            ReplayEvent::Strike { strike, .. } => Some(strike),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+pub enum ReplayEvent {
+    // an action accepted from a player, along with the word it produced
+    Action {
+        frame: u32,
+        side: PlayerSide,
+        action: Action,
+        word: Word,
+    },
+    Strike {
+        frame: u32,
+        strike: Strike,
+    },
+}
+
+// Where finished games are written to. Replays are only saved when this resource exists.
+#[derive(Clone, Debug)]
+#[derive(Deref, Resource)]
+pub struct ReplayDirectory(pub PathBuf);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+pub struct Replay {
+    pub version: u32,
+    pub arena_size: usize,
+    #[serde(flatten)]
+    pub settings: GameSettings,
+    pub events: Vec<ReplayEvent>,
+    pub result: GameResult,
+}
+
+impl Replay {
+    pub fn new(arena: &Arena, log: &GameLog, result: GameResult) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            arena_size: arena.size(),
+            settings: GameSettings::default(),
+            events: log.events().to_vec(),
+            result,
+        }
+    }
+
+    pub fn with_settings(mut self, settings: GameSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String, ReplayError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ReplayError> {
+        let replay: Self = serde_json::from_str(json)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(replay.version));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    fn strikes(&self) -> Vec<Strike> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                ReplayEvent::Strike { strike, .. } => Some(*strike),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Feeds the recorded actions back through a fresh headless game,
+    // checking that it produces the same strikes and the same result.
+    pub fn verify(&self) -> Result<(), ReplayError> {
+        self.verify_with(Dictionaries::default())
+    }
+
+    // Like `verify`, for replays of games that used dictionaries loaded at runtime
+    pub fn verify_with(&self, dictionaries: Dictionaries) -> Result<(), ReplayError> {
+        if !dictionaries.contains(&self.settings.dictionary) {
+            return Err(ReplayError::MissingDictionary(
+                self.settings.dictionary.clone(),
+            ));
+        }
+        let mut app = App::new();
+        app.insert_resource(dictionaries);
+        app.add_plugins((MinimalPlugins, WordFightGamePlugin));
+        app.update();
+
+        let left = app
+            .world_mut()
+            .spawn(Client::from(LEFT_CLIENT).bundle())
+            .id();
+        let right = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut().trigger(
+            SpawnGame::new(self.arena_size, left, right).with_settings(self.settings.clone()),
+        );
+        app.update();
+        let game = **app
+            .world()
+            .get::<InGame>(left)
+            .expect("replay game to be spawned");
+
+        let actions = self
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                ReplayEvent::Action {
+                    frame,
+                    side,
+                    action,
+                    ..
+                } => Some((*frame, *side, *action)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // actions from the same frame must be processed on the same frame to reproduce contact
+        for frame_actions in actions.chunk_by(|(frame1, ..), (frame2, ..)| frame1 == frame2) {
+            for (_, side, action) in frame_actions {
+                let client_id = if side.is_left() {
+                    LEFT_CLIENT
+                } else {
+                    RIGHT_CLIENT
+                };
+                app.world_mut().send_event(FromClient {
+                    client_id,
+                    event: ActionEvent::from(*action),
+                });
+            }
+            app.update();
+        }
+
+        let strikes = app
+            .world()
+            .get::<GameLog>(game)
+            .map(|log| log.strikes().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let expected_strikes = self.strikes();
+        if strikes != expected_strikes {
+            return Err(ReplayError::StrikesMismatch {
+                expected: expected_strikes,
+                found: strikes,
+            });
+        }
+        let score = |player| app.world().get::<Score>(player).map_or(0, |score| **score);
+        // games the server ended early only have to get as far as the final score
+        let result = if self.result.aborted {
+            Some(GameResult::aborted(score(left), score(right)))
+        } else if let (true, Some(winner)) = (self.result.forfeit, self.result.winner) {
+            Some(GameResult::forfeit(winner, score(left), score(right)))
+        } else {
+            app.world().get::<GameResult>(game).copied()
+        };
+        if result != Some(self.result) {
+            return Err(ReplayError::ResultMismatch {
+                expected: self.result,
+                found: result,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Failed to read or write replay: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to (de)serialize replay: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported replay version {0} (expected {REPLAY_VERSION})")]
+    UnsupportedVersion(u32),
+    #[error("Replay needs the {0} dictionary, which is not loaded")]
+    MissingDictionary(DictionaryName),
+    #[error("Replay produced strikes {found:?}, expected {expected:?}")]
+    StrikesMismatch {
+        expected: Vec<Strike>,
+        found: Vec<Strike>,
+    },
+    #[error("Replay produced result {found:?}, expected {expected:?}")]
+    ResultMismatch {
+        expected: GameResult,
+        found: Option<GameResult>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameSettingsQuery, Letter, PlayerSide, WinCondition};
+
+    // records a short game where the left player wins with "alphabet" vs "a"
+    fn record_game() -> Replay {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, WordFightGamePlugin));
+        app.update();
+
+        let left = app
+            .world_mut()
+            .spawn(Client::from(LEFT_CLIENT).bundle())
+            .id();
+        let right = app
+            .world_mut()
+            .spawn(Client::from(RIGHT_CLIENT).bundle())
+            .id();
+        app.world_mut()
+            .trigger(SpawnGame::new(7, left, right).with_settings(
+                GameSettings::default().with_win_condition(WinCondition::Points(1)),
+            ));
+        app.update();
+
+        let mut send_action = |client_id, action| {
+            app.world_mut().send_event(FromClient {
+                client_id,
+                event: ActionEvent::from(action),
+            });
+            app.update();
+        };
+        send_action(RIGHT_CLIENT, Action::Append(Letter::A));
+        for letter in [Letter::A, Letter::L, Letter::P, Letter::H, Letter::A] {
+            send_action(LEFT_CLIENT, Action::Append(letter));
+        }
+        send_action(LEFT_CLIENT, Action::Append(Letter::B));
+        assert_eq!(**app.world().get::<Score>(left).unwrap(), 1);
+
+        let game = **app.world().get::<InGame>(left).unwrap();
+        let settings = app
+            .world_mut()
+            .query::<GameSettingsQuery>()
+            .get(app.world(), game)
+            .unwrap()
+            .settings();
+        let world = app.world();
+        Replay::new(
+            world.get::<Arena>(game).unwrap(),
+            world.get::<GameLog>(game).unwrap(),
+            *world.get::<GameResult>(game).unwrap(),
+        )
+        .with_settings(settings)
+    }
+
+    #[test]
+    fn test_replay_roundtrip() {
+        let replay = record_game();
+        assert_eq!(replay.strikes().len(), 1);
+        let json = replay.to_json().unwrap();
+        let loaded = Replay::from_json(&json).unwrap();
+        assert_eq!(loaded, replay);
+        loaded.verify().unwrap();
+    }
+
+    #[test]
+    fn test_replay_detects_mismatch() {
+        let mut replay = record_game();
+        replay.result.winner = Some(PlayerSide::Right);
+        assert!(matches!(
+            replay.verify(),
+            Err(ReplayError::ResultMismatch { .. })
+        ));
+
+        let mut replay = record_game();
+        replay.version += 1;
+        let json = replay.to_json().unwrap();
+        assert!(matches!(
+            Replay::from_json(&json),
+            Err(ReplayError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use bevy::prelude::*;
+
+pub const ROOM_CODE_LENGTH: usize = 5;
+// no 0/O or 1/I, since codes are meant to be read out to friends
+const ROOM_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+// A short code that identifies a private room, for sharing with whoever you want to play
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct RoomCode(String);
+
+impl RoomCode {
+    // builds a code out of random bits, e.g. from the server's entropy
+    pub fn from_random(mut random: u64) -> Self {
+        let base = ROOM_CODE_ALPHABET.len() as u64;
+        let code = (0..ROOM_CODE_LENGTH)
+            .map(|_| {
+                let index = (random % base) as usize;
+                random /= base;
+                ROOM_CODE_ALPHABET[index] as char
+            })
+            .collect();
+        RoomCode(code)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for RoomCode {
+    type Err = RoomCodeError;
+
+    // codes are forgiving of case and surrounding whitespace, since people type them in
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let code = code.trim().to_ascii_uppercase();
+        let length = code.chars().count();
+        if length != ROOM_CODE_LENGTH {
+            return Err(RoomCodeError::Length(length));
+        }
+        if let Some(character) = code.chars().find(|character| {
+            !character.is_ascii() || !ROOM_CODE_ALPHABET.contains(&(*character as u8))
+        }) {
+            return Err(RoomCodeError::Character(character));
+        }
+        Ok(RoomCode(code))
+    }
+}
+
+impl std::fmt::Display for RoomCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RoomCodeError {
+    #[error("Room codes are {ROOM_CODE_LENGTH} characters long, not {0}")]
+    Length(usize),
+    #[error("Room codes cannot contain {0:?}")]
+    Character(char),
+}
+
+// Added to clients in a private room. They are matched with the other member of their room
+// (whenever both are free) and never with anyone else.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Component, Deref, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct InRoom(RoomCode);
+
+impl InRoom {
+    pub fn new(code: RoomCode) -> Self {
+        InRoom(code)
+    }
+}
+
+// Sent by clients to create, join or leave a private room
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub enum RoomRequest {
+    Create,
+    Join(RoomCode),
+    Leave,
+}
+
+// Sent to a client in reply to each of its room requests
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub enum RoomResponse {
+    // the client is now in this room, waiting for someone to join
+    Created(RoomCode),
+    // the client is now in this room, along with whoever created it
+    Joined(RoomCode),
+    Left,
+    NotFound(RoomCode),
+    // both players are already in this room
+    Full(RoomCode),
+    // rooms cannot be created or joined in the middle of a game
+    InGame,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_room_codes() {
+        let code = RoomCode::from_random(0x0123_4567_89ab_cdef);
+        assert_eq!(code.as_str().len(), ROOM_CODE_LENGTH);
+        assert_ne!(code, RoomCode::from_random(0xfedc_ba98_7654_3210));
+        assert_eq!(code.to_string().parse(), Ok(code.clone()));
+        assert_eq!(
+            format!(" {} ", code.as_str().to_ascii_lowercase()).parse(),
+            Ok(code)
+        );
+
+        assert_eq!("ABC".parse::<RoomCode>(), Err(RoomCodeError::Length(3)));
+        assert_eq!(
+            "ABC0E".parse::<RoomCode>(),
+            Err(RoomCodeError::Character('0'))
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+
+use crate::{Letter, PlayerSide, Strike, StrikeBonus, WordList};
+
+// Decides which of the two striking letters wins.
+// Lives on the Game entity so that each game can pick its own variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Component, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub enum StrikeRule {
+    // letters later in the alphabet win
+    #[default]
+    Alphabetical,
+    // letters earlier in the alphabet win
+    ReverseAlphabetical,
+    // letters worth more Scrabble points win
+    LetterValue,
+    // letters that appear less often in the game's dictionary win
+    Rarity,
+}
+
+impl StrikeRule {
+    pub const ALL: [StrikeRule; 4] = [
+        StrikeRule::Alphabetical,
+        StrikeRule::ReverseAlphabetical,
+        StrikeRule::LetterValue,
+        StrikeRule::Rarity,
+    ];
+
+    pub fn power(&self, letter: Letter, words: &WordList) -> usize {
+        match self {
+            StrikeRule::Alphabetical => letter.index(),
+            StrikeRule::ReverseAlphabetical => Letter::ALL.len() - 1 - letter.index(),
+            StrikeRule::LetterValue => letter_value(letter),
+            StrikeRule::Rarity => words.rarity(letter),
+        }
+    }
+
+    // A missing letter (i.e. an empty word) always loses to any letter.
+    pub fn compare(
+        &self,
+        left: Option<Letter>,
+        right: Option<Letter>,
+        words: &WordList,
+    ) -> Ordering {
+        let left = left.map(|letter| self.power(letter, words));
+        let right = right.map(|letter| self.power(letter, words));
+        left.cmp(&right)
+    }
+}
+
+impl std::fmt::Display for StrikeRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            StrikeRule::Alphabetical => "Alphabetical",
+            StrikeRule::ReverseAlphabetical => "Reverse Alphabetical",
+            StrikeRule::LetterValue => "Letter Value",
+            StrikeRule::Rarity => "Rarity",
+        };
+        write!(f, "{name}")
+    }
+}
+
+fn letter_value(letter: Letter) -> usize {
+    use Letter::*;
+    match letter {
+        A | E | I | L | N | O | R | S | T | U => 1,
+        D | G => 2,
+        B | C | M | P => 3,
+        F | H | V | W | Y => 4,
+        K => 5,
+        J | X => 8,
+        Q | Z => 10,
+    }
+}
+
+// Which partial words players may type. Lives on the Game entity next to its StrikeRule.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Component, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub enum ValidationMode {
+    // every word must be the start of some word in the dictionary
+    #[default]
+    Prefix,
+    // every word must appear somewhere inside some word in the dictionary, e.g. "ltr" (paltry)
+    Infix,
+}
+
+impl std::fmt::Display for ValidationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ValidationMode::Prefix => "Prefix",
+            ValidationMode::Infix => "Infix",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// Optionally rewards striking with a complete word rather than just part of one.
+// Lives on the Game entity next to its StrikeRule.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Component, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub enum CompleteWordBonus {
+    #[default]
+    Off,
+    // a complete word wins a strike that would otherwise be a parry
+    TieBreak,
+    // like TieBreak, and a complete word that wins on its letters also scores an extra point
+    ExtraPoint,
+}
+
+impl CompleteWordBonus {
+    // the most points a single strike can be worth
+    pub fn max_points(&self) -> usize {
+        match self {
+            CompleteWordBonus::ExtraPoint => 2,
+            CompleteWordBonus::Off | CompleteWordBonus::TieBreak => 1,
+        }
+    }
+
+    pub fn apply(&self, strike: Strike, left_complete: bool, right_complete: bool) -> Strike {
+        let is_complete = |side: PlayerSide| match side {
+            PlayerSide::Left => left_complete,
+            PlayerSide::Right => right_complete,
+        };
+        match (self, strike) {
+            (CompleteWordBonus::Off, _) => strike,
+            (_, Strike::Parry) if left_complete != right_complete => {
+                let side = if left_complete {
+                    PlayerSide::Left
+                } else {
+                    PlayerSide::Right
+                };
+                Strike::Score(side, Some(StrikeBonus::TieBreak))
+            }
+            (CompleteWordBonus::ExtraPoint, Strike::Score(side, None)) if is_complete(side) => {
+                Strike::Score(side, Some(StrikeBonus::CompleteWord))
+            }
+            _ => strike,
+        }
+    }
+}
+
+impl std::fmt::Display for CompleteWordBonus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CompleteWordBonus::Off => "Off",
+            CompleteWordBonus::TieBreak => "Tie Break",
+            CompleteWordBonus::ExtraPoint => "Extra Point",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strike_rule_letter_value() {
+        let words = WordList::new(["pal"]);
+        let rule = StrikeRule::LetterValue;
+        // M comes after K, but K is worth more
+        assert_eq!(
+            rule.compare(Some(Letter::K), Some(Letter::M), &words),
+            Ordering::Greater
+        );
+        assert_eq!(
+            StrikeRule::Alphabetical.compare(Some(Letter::K), Some(Letter::M), &words),
+            Ordering::Less
+        );
+        // letters worth the same parry
+        assert_eq!(
+            rule.compare(Some(Letter::Q), Some(Letter::Z), &words),
+            Ordering::Equal
+        );
+        assert_eq!(rule.compare(None, Some(Letter::A), &words), Ordering::Less);
+    }
+
+    #[test]
+    fn test_complete_word_bonus() {
+        let left = Strike::Score(PlayerSide::Left, None);
+        assert_eq!(
+            CompleteWordBonus::Off.apply(Strike::Parry, true, false),
+            Strike::Parry
+        );
+        assert_eq!(CompleteWordBonus::Off.apply(left, true, false), left);
+
+        // ties are only broken when exactly one word is complete
+        assert_eq!(
+            CompleteWordBonus::TieBreak.apply(Strike::Parry, false, true),
+            Strike::Score(PlayerSide::Right, Some(StrikeBonus::TieBreak))
+        );
+        assert_eq!(
+            CompleteWordBonus::TieBreak.apply(Strike::Parry, true, true),
+            Strike::Parry
+        );
+        assert_eq!(CompleteWordBonus::TieBreak.apply(left, true, false), left);
+
+        let bonus = CompleteWordBonus::ExtraPoint.apply(left, true, false);
+        assert_eq!(
+            bonus,
+            Strike::Score(PlayerSide::Left, Some(StrikeBonus::CompleteWord))
+        );
+        assert_eq!(bonus.points(), 2);
+        // the loser's complete word does not matter
+        assert_eq!(CompleteWordBonus::ExtraPoint.apply(left, false, true), left);
+        assert_eq!(
+            CompleteWordBonus::ExtraPoint.apply(Strike::OverRange, true, false),
+            Strike::OverRange
+        );
+    }
+}
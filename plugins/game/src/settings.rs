@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use bevy::{ecs::query::QueryData, prelude::*};
+
+use crate::{CompleteWordBonus, DictionaryName, StrikeRule, ValidationMode, WinCondition};
+
+// The rules a game is played with. Each setting is its own component on the Game entity,
+// so systems only ask for the ones they need.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Bundle)]
+#[derive(Deserialize, Serialize)]
+pub struct GameSettings {
+    pub strike_rule: StrikeRule,
+    pub validation_mode: ValidationMode,
+    pub win_condition: WinCondition,
+    pub dictionary: DictionaryName,
+    pub complete_word_bonus: CompleteWordBonus,
+}
+
+impl GameSettings {
+    pub fn with_strike_rule(mut self, strike_rule: StrikeRule) -> Self {
+        self.strike_rule = strike_rule;
+        self
+    }
+
+    pub fn with_validation_mode(mut self, validation_mode: ValidationMode) -> Self {
+        self.validation_mode = validation_mode;
+        self
+    }
+
+    pub fn with_win_condition(mut self, win_condition: WinCondition) -> Self {
+        self.win_condition = win_condition;
+        self
+    }
+
+    pub fn with_dictionary(mut self, dictionary: DictionaryName) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    pub fn with_complete_word_bonus(mut self, complete_word_bonus: CompleteWordBonus) -> Self {
+        self.complete_word_bonus = complete_word_bonus;
+        self
+    }
+}
+
+// Reads a game's `GameSettings` back from its components.
+#[derive(QueryData)]
+pub struct GameSettingsQuery {
+    pub strike_rule: &'static StrikeRule,
+    pub validation_mode: &'static ValidationMode,
+    pub win_condition: &'static WinCondition,
+    pub dictionary: &'static DictionaryName,
+    pub complete_word_bonus: &'static CompleteWordBonus,
+}
+
+impl GameSettingsQueryItem<'_> {
+    pub fn settings(&self) -> GameSettings {
+        GameSettings {
+            strike_rule: *self.strike_rule,
+            validation_mode: *self.validation_mode,
+            win_condition: *self.win_condition,
+            dictionary: self.dictionary.clone(),
+            complete_word_bonus: *self.complete_word_bonus,
+        }
+    }
+}
@@ -1,36 +1,428 @@
+use serde::{Deserialize, Serialize};
+use std::{path::Path, sync::OnceLock, time::Duration};
+use thiserror::Error;
+
 use bevy::ecs::system::SystemParam;
-use bevy::prelude::{Reflect, Res, Resource};
+use bevy::prelude::{Component, Deref, Event, EventWriter, Reflect, Res, Resource};
+use bevy::utils::{HashMap, Instant};
+
+use crate::{Letter, ValidationMode};
 
 // Taken from https://github.com/dwyl/english-words/blob/master/words_alpha.txt
-// TODO: only compile this for server...?
+// Clients never validate words themselves, so they can leave this out of their binaries.
+#[cfg(feature = "embedded-wordlist")]
 pub const WORD_LIST: &str = include_str!("wordlist.txt");
 
+// The dictionary games use unless they pick another one.
+pub const DEFAULT_DICTIONARY: &str = "english";
+
+// stands in for dictionaries that have not been loaded
+static EMPTY_WORD_LIST: WordList = WordList::empty();
+
+// Words are kept lowercased, sorted and deduplicated so that every word sharing a prefix
+// sits in one contiguous run, which lets prefix queries binary search instead of scanning.
 #[derive(Debug)]
-#[derive(Resource, Reflect)]
-pub struct WordList(Vec<String>);
+#[derive(Reflect)]
+pub struct WordList {
+    words: Vec<String>,
+    // rank of each letter by how rarely it appears in the list, 0 being the most common
+    rarity: [usize; 26],
+    // only built once a game actually asks for infix matching
+    #[reflect(ignore)]
+    infixes: OnceLock<InfixIndex>,
+}
 
 impl Default for WordList {
+    #[cfg(feature = "embedded-wordlist")]
+    fn default() -> Self {
+        Self::new(WORD_LIST.lines())
+    }
+
+    #[cfg(not(feature = "embedded-wordlist"))]
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl WordList {
+    pub fn new<S: AsRef<str>>(words: impl IntoIterator<Item = S>) -> Self {
+        let mut words = words
+            .into_iter()
+            .map(|word| word.as_ref().trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        words.sort_unstable();
+        words.dedup();
+
+        let mut counts = [0usize; 26];
+        for letter in words
+            .iter()
+            .flat_map(|word| word.chars())
+            .filter_map(Letter::from_char)
+        {
+            counts[letter.index()] += 1;
+        }
+        let mut by_frequency = Letter::ALL;
+        by_frequency.sort_by_key(|letter| std::cmp::Reverse(counts[letter.index()]));
+        let mut rarity = [0; 26];
+        for (rank, letter) in by_frequency.into_iter().enumerate() {
+            rarity[letter.index()] = rank;
+        }
+
+        Self {
+            words,
+            rarity,
+            infixes: OnceLock::new(),
+        }
+    }
+
+    pub const fn empty() -> Self {
+        Self {
+            words: Vec::new(),
+            rarity: [0; 26],
+            infixes: OnceLock::new(),
+        }
+    }
+
+    // Expects one word per line, in UTF-8.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DictionaryError> {
+        Ok(Self::new(std::str::from_utf8(bytes)?.lines()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DictionaryError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn rarity(&self, letter: Letter) -> usize {
+        self.rarity[letter.index()]
+    }
+
+    // `prefix` is expected to already be lowercase.
+    pub fn is_prefix(&self, prefix: &str) -> bool {
+        !self.continuations(prefix).is_empty()
+    }
+
+    // `word` is expected to already be lowercase.
+    pub fn is_word(&self, word: &str) -> bool {
+        self.words
+            .binary_search_by(|candidate| candidate.as_str().cmp(word))
+            .is_ok()
+    }
+
+    // Whether `infix` appears anywhere inside some word.
+    // `infix` is expected to already be lowercase.
+    pub fn is_infix(&self, infix: &str) -> bool {
+        self.infixes
+            .get_or_init(|| InfixIndex::new(&self.words))
+            .contains(infix)
+    }
+
+    pub fn is_word_substring(&self, test_string: &str, mode: ValidationMode) -> bool {
+        let test_string = test_string.to_lowercase();
+        match mode {
+            ValidationMode::Prefix => self.is_prefix(&test_string),
+            ValidationMode::Infix => self.is_infix(&test_string),
+        }
+    }
+
+    // All words starting with `prefix` (including `prefix` itself, if it is a word).
+    // `prefix` is expected to already be lowercase.
+    pub fn continuations(&self, prefix: &str) -> &[String] {
+        let start = self.words.partition_point(|word| word.as_str() < prefix);
+        let rest = &self.words[start..];
+        let len = rest.partition_point(|word| word.starts_with(prefix));
+        &rest[..len]
+    }
+}
+
+// A suffix array over every word: each position in each word, sorted by the rest of the
+// text from there on. Any infix is a prefix of one of those suffixes, so like prefix lookups,
+// infix lookups become a binary search.
+#[derive(Debug, Default)]
+struct InfixIndex {
+    // all words, each followed by a newline. Newlines sort before any letter, so comparing
+    // whole suffixes of the text orders them the same way as comparing just the rest of each word.
+    text: Vec<u8>,
+    suffixes: Vec<u32>,
+}
+
+impl InfixIndex {
+    fn new(words: &[String]) -> Self {
+        let mut text = Vec::with_capacity(words.iter().map(|word| word.len() + 1).sum());
+        for word in words {
+            text.extend_from_slice(word.as_bytes());
+            text.push(b'\n');
+        }
+        let mut suffixes = (0..text.len() as u32)
+            .filter(|index| text[*index as usize] != b'\n')
+            .collect::<Vec<_>>();
+        suffixes.sort_unstable_by(|a, b| text[*a as usize..].cmp(&text[*b as usize..]));
+        Self { text, suffixes }
+    }
+
+    fn contains(&self, infix: &str) -> bool {
+        let infix = infix.as_bytes();
+        let window = |start: u32| {
+            let start = start as usize;
+            &self.text[start..self.text.len().min(start + infix.len())]
+        };
+        let start = self
+            .suffixes
+            .partition_point(|start| window(*start) < infix);
+        // infixes never contain newlines, so a match never runs across two words
+        self.suffixes
+            .get(start)
+            .is_some_and(|start| window(*start) == infix)
+    }
+}
+
+// Every dictionary the server can run games with, by name.
+#[derive(Debug)]
+#[derive(Resource)]
+pub struct Dictionaries(HashMap<String, WordList>);
+
+impl Default for Dictionaries {
+    #[cfg(feature = "embedded-wordlist")]
+    fn default() -> Self {
+        let mut dictionaries = Self::empty();
+        dictionaries.insert(DEFAULT_DICTIONARY, WordList::default());
+        dictionaries
+    }
+
+    #[cfg(not(feature = "embedded-wordlist"))]
     fn default() -> Self {
-        Self(
-            WORD_LIST
-                .lines()
-                .map(|word| word.to_lowercase())
-                .collect::<Vec<_>>(),
-        )
+        Self::empty()
     }
 }
 
+impl Dictionaries {
+    pub fn empty() -> Self {
+        Dictionaries(HashMap::default())
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, words: WordList) {
+        self.0.insert(name.into(), words);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WordList> {
+        self.0.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    // Loads every `.txt` file in `directory` as a dictionary named after the file,
+    // e.g. `english-common.txt` becomes "english-common". Returns the names loaded.
+    pub fn load_directory(
+        &mut self,
+        directory: impl AsRef<Path>,
+    ) -> Result<Vec<String>, DictionaryError> {
+        let mut loaded = Vec::new();
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("txt") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let name = name.to_string();
+            self.insert(name.clone(), WordList::load(&path)?);
+            loaded.push(name);
+        }
+        Ok(loaded)
+    }
+}
+
+// Which of the `Dictionaries` a game checks words against. Lives on the Game entity.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Component, Deref, Reflect)]
+#[derive(Deserialize, Serialize)]
+pub struct DictionaryName(String);
+
+impl DictionaryName {
+    pub fn new(name: impl Into<String>) -> Self {
+        DictionaryName(name.into())
+    }
+}
+
+impl Default for DictionaryName {
+    fn default() -> Self {
+        DictionaryName::new(DEFAULT_DICTIONARY)
+    }
+}
+
+impl std::fmt::Display for DictionaryName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Sent each time `Dictionary::lookup` checks an action against its dictionary.
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+pub struct DictionaryLookup {
+    pub duration: Duration,
+}
+
 #[derive(SystemParam)]
 pub struct Dictionary<'w> {
-    words: Res<'w, WordList>,
+    dictionaries: Res<'w, Dictionaries>,
+    lookups: EventWriter<'w, DictionaryLookup>,
 }
 
 impl<'w> Dictionary<'w> {
-    pub fn is_word_substring(&self, test_string: &str) -> bool {
-        let test_string = test_string.to_lowercase();
-        self.words
-            .0
-            .iter()
-            .any(|word| word.starts_with(&test_string))
+    pub fn dictionaries(&self) -> &Dictionaries {
+        &self.dictionaries
+    }
+
+    // Resolves a game's dictionary. Games are only spawned with dictionaries that exist,
+    // but an unknown name resolves to an empty list that rejects every word.
+    pub fn get(&self, name: &DictionaryName) -> &WordList {
+        self.dictionaries.get(name).unwrap_or(&EMPTY_WORD_LIST)
+    }
+
+    // Like `get`, but sends a `DictionaryLookup` with how long `lookup` took.
+    pub fn lookup<T>(&mut self, name: &DictionaryName, lookup: impl FnOnce(&WordList) -> T) -> T {
+        let start = Instant::now();
+        let result = lookup(self.dictionaries.get(name).unwrap_or(&EMPTY_WORD_LIST));
+        self.lookups.send(DictionaryLookup {
+            duration: start.elapsed(),
+        });
+        result
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DictionaryError {
+    #[error("Failed to read dictionary: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Dictionary is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word_list() -> WordList {
+        WordList::new([
+            "paltry",
+            "Patronize",
+            "pal",
+            "pat",
+            "zebra",
+            "pal",
+            "",
+            "apple",
+        ])
+    }
+
+    #[test]
+    fn test_word_list_is_sorted_and_deduplicated() {
+        let words = word_list();
+        assert_eq!(
+            words.words(),
+            ["apple", "pal", "paltry", "pat", "patronize", "zebra"]
+        );
+    }
+
+    #[test]
+    fn test_word_list_prefixes() {
+        let words = word_list();
+        assert!(words.is_prefix("p"));
+        assert!(words.is_prefix("pal"));
+        assert!(words.is_prefix("patr"));
+        assert!(words.is_prefix("zebra"));
+        assert!(!words.is_prefix("ltr"));
+        assert!(!words.is_prefix("zebras"));
+        assert!(!words.is_prefix("b"));
+        assert!(words.is_prefix(""));
+    }
+
+    #[test]
+    fn test_word_list_continuations() {
+        let words = word_list();
+        assert_eq!(
+            words.continuations("pa"),
+            ["pal", "paltry", "pat", "patronize"]
+        );
+        assert_eq!(words.continuations("pal"), ["pal", "paltry"]);
+        assert_eq!(words.continuations("z"), ["zebra"]);
+        assert!(words.continuations("q").is_empty());
+        assert_eq!(words.continuations("").len(), words.len());
+    }
+
+    #[test]
+    fn test_word_list_words() {
+        let words = word_list();
+        assert!(words.is_word("pal"));
+        assert!(words.is_word("patronize"));
+        assert!(!words.is_word("patr"));
+        assert!(!words.is_word("zebras"));
+        assert!(!words.is_word(""));
+    }
+
+    #[test]
+    fn test_word_list_infixes() {
+        let words = word_list();
+        assert!(words.is_infix("ltr"));
+        assert!(words.is_infix("onize"));
+        assert!(words.is_infix("bra"));
+        assert!(words.is_infix("pal"));
+        assert!(words.is_infix(""));
+        // infixes never run across two words
+        assert!(!words.is_infix("ep"));
+        assert!(!words.is_infix("yp"));
+        assert!(!words.is_infix("qq"));
+
+        assert!(!words.is_word_substring("LTR", ValidationMode::Prefix));
+        assert!(words.is_word_substring("LTR", ValidationMode::Infix));
+    }
+
+    #[test]
+    fn test_word_list_from_bytes() {
+        let words = WordList::from_bytes(b"Zebra\r\npal\n\npat\n").unwrap();
+        assert_eq!(words.words(), ["pal", "pat", "zebra"]);
+        assert!(words.is_word_substring("PA", ValidationMode::Prefix));
+        assert!(matches!(
+            WordList::from_bytes(&[0x70, 0xff]),
+            Err(DictionaryError::Utf8(_))
+        ));
+    }
+
+    #[test]
+    fn test_dictionaries_by_name() {
+        let mut dictionaries = Dictionaries::empty();
+        dictionaries.insert("tiny", word_list());
+        dictionaries.insert("other", WordList::new(["zebra"]));
+        assert!(dictionaries.get("tiny").unwrap().is_prefix("pat"));
+        assert!(!dictionaries.get("other").unwrap().is_prefix("pat"));
+        assert!(dictionaries.get(DEFAULT_DICTIONARY).is_none());
+    }
+
+    #[test]
+    fn test_word_list_rarity() {
+        let words = WordList::new(["eel", "see", "el"]);
+        assert_eq!(words.rarity(Letter::E), 0);
+        assert!(words.rarity(Letter::L) < words.rarity(Letter::S));
+        assert!(words.rarity(Letter::S) < words.rarity(Letter::Z));
     }
 }
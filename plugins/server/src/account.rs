@@ -0,0 +1,92 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+
+use bevy_replicon_renet2::renet2::transport::{generate_random_bytes, NETCODE_USER_DATA_BYTES};
+
+use crate::{PlayerProfile, Storage, StorageError};
+
+pub const ACCOUNT_KEY_BYTES: usize = 32;
+
+// The secret a client signs in with. Clients are handed one along with their first `Welcome`
+// and pass it back (as text) when fetching connect tokens.
+pub type AccountKey = [u8; ACCOUNT_KEY_BYTES];
+
+pub fn encode_key(key: &AccountKey) -> String {
+    URL_SAFE_NO_PAD.encode(key)
+}
+
+pub fn decode_key(key: &str) -> Option<AccountKey> {
+    URL_SAFE_NO_PAD.decode(key.trim()).ok()?.try_into().ok()
+}
+
+// Finds the player `key` belongs to, or makes a new player (with a new key) if there is none.
+pub fn sign_in(
+    storage: &mut dyn Storage,
+    key: Option<AccountKey>,
+    now: u64,
+) -> Result<(PlayerProfile, AccountKey), StorageError> {
+    if let Some(key) = key {
+        if let Some(player) = find_player(storage, &key)? {
+            return Ok((player, key));
+        }
+    }
+    let key = generate_random_bytes::<ACCOUNT_KEY_BYTES>();
+    let player = storage.create_player(&hash_key(&key), now)?;
+    Ok((player, key))
+}
+
+pub fn find_player(
+    storage: &dyn Storage,
+    key: &AccountKey,
+) -> Result<Option<PlayerProfile>, StorageError> {
+    storage.player_by_key(&hash_key(key))
+}
+
+fn hash_key(key: &AccountKey) -> Vec<u8> {
+    Sha256::digest(key).to_vec()
+}
+
+// Connect tokens carry the key to the game server in their user data, where the first byte
+// says whether there is one at all.
+pub(crate) fn key_to_user_data(key: Option<AccountKey>) -> [u8; NETCODE_USER_DATA_BYTES] {
+    let mut user_data = [0; NETCODE_USER_DATA_BYTES];
+    if let Some(key) = key {
+        user_data[0] = 1;
+        user_data[1..=ACCOUNT_KEY_BYTES].copy_from_slice(&key);
+    }
+    user_data
+}
+
+pub(crate) fn key_from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Option<AccountKey> {
+    if user_data[0] != 1 {
+        return None;
+    }
+    user_data[1..=ACCOUNT_KEY_BYTES].try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStorage;
+
+    #[test]
+    fn test_sign_in() {
+        let mut storage = MemoryStorage::default();
+        let (player, key) = sign_in(&mut storage, None, 100).unwrap();
+        assert_eq!(player.created_at, 100);
+
+        let key = decode_key(&encode_key(&key)).unwrap();
+        let key = key_from_user_data(&key_to_user_data(Some(key)));
+        assert_eq!(key_from_user_data(&key_to_user_data(None)), None);
+        let (same_player, same_key) = sign_in(&mut storage, key, 200).unwrap();
+        assert_eq!(same_player, player);
+        assert_eq!(Some(same_key), key);
+
+        // a key nobody has gets a fresh account rather than claiming that key
+        let unknown = [9; ACCOUNT_KEY_BYTES];
+        let (other_player, other_key) = sign_in(&mut storage, Some(unknown), 300).unwrap();
+        assert_ne!(other_player.id, player.id);
+        assert_ne!(other_key, unknown);
+        assert_eq!(decode_key("not a key"), None);
+    }
+}
@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::Infallible,
+    sync::{Arc, PoisonError, RwLock},
+};
+use warp::{
+    http::StatusCode,
+    reply::{json, with_status, Json, WithStatus},
+    Filter, Rejection, Reply,
+};
+
+use bevy::{log::error, prelude::Resource};
+
+use game::{GameSettings, PlayerId, Rating};
+
+use crate::{MatchId, MatchRecord, Persistence, PlayerProfile, Storage, StorageError};
+
+// how many players `/leaderboard` lists unless asked for fewer
+const LEADERBOARD_LIMIT: usize = 100;
+// how many of a player's most recent matches `/players/{id}` lists
+const PLAYER_MATCHES_LIMIT: usize = 20;
+
+// A game that is being played right now, as listed by `/live`.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+pub struct LiveGame {
+    // the game's entity, which identifies it until it ends
+    pub game: u64,
+    pub left: LivePlayer,
+    pub right: LivePlayer,
+    pub arena_size: usize,
+    #[serde(flatten)]
+    pub settings: GameSettings,
+    // seconds the game has been running for
+    pub elapsed_secs: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+pub struct LivePlayer {
+    // bots (and clients the server failed to sign in) have no player id
+    pub player: Option<PlayerId>,
+    pub rating: Option<Rating>,
+    pub is_bot: bool,
+    pub score: usize,
+}
+
+// The games being played, as last seen by the server. Kept up to date by the server
+// every frame and read by the HTTP API.
+#[derive(Clone, Default)]
+#[derive(Resource)]
+pub struct LiveGames(Arc<RwLock<Vec<LiveGame>>>);
+
+impl LiveGames {
+    pub fn get(&self) -> Vec<LiveGame> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn set(&self, games: Vec<LiveGame>) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = games;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    #[serde(flatten)]
+    pub player: PlayerProfile,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+pub struct PlayerDetails {
+    #[serde(flatten)]
+    pub player: PlayerProfile,
+    // newest first
+    pub matches: Vec<MatchDetails>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+pub struct MatchDetails {
+    pub id: MatchId,
+    #[serde(flatten)]
+    pub record: MatchRecord,
+}
+
+#[derive(Debug)]
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Debug)]
+#[derive(Serialize)]
+struct ApiError {
+    error: String,
+}
+
+// The read-only JSON routes served next to `/token`:
+// `/leaderboard`, `/players/{id}`, `/matches/{id}` and `/live`.
+pub(crate) fn routes(
+    persistence: Persistence,
+    live_games: LiveGames,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let with_persistence = warp::any().map(move || persistence.clone());
+
+    let leaderboard = warp::path("leaderboard")
+        .and(warp::path::end())
+        .and(warp::query::<LeaderboardQuery>())
+        .and(with_persistence.clone())
+        .and_then(|query: LeaderboardQuery, persistence: Persistence| {
+            let limit = query
+                .limit
+                .unwrap_or(LEADERBOARD_LIMIT)
+                .min(LEADERBOARD_LIMIT);
+            read(persistence, move |storage| {
+                let players = storage.top_players(limit)?;
+                Ok(Some(
+                    players
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, player)| LeaderboardEntry {
+                            rank: index + 1,
+                            player,
+                        })
+                        .collect::<Vec<_>>(),
+                ))
+            })
+        });
+    let player = warp::path!("players" / u64)
+        .and(with_persistence.clone())
+        .and_then(|id, persistence: Persistence| {
+            let id = PlayerId::new(id);
+            read(persistence, move |storage| {
+                let Some(player) = storage.player(id)? else {
+                    return Ok(None);
+                };
+                let matches = storage
+                    .player_matches(id, PLAYER_MATCHES_LIMIT)?
+                    .into_iter()
+                    .map(|(id, record)| MatchDetails { id, record })
+                    .collect();
+                Ok(Some(PlayerDetails { player, matches }))
+            })
+        });
+    let match_record = warp::path!("matches" / MatchId)
+        .and(with_persistence)
+        .and_then(|id, persistence: Persistence| {
+            read(persistence, move |storage| {
+                let record = storage.match_record(id)?;
+                Ok(record.map(|record| MatchDetails { id, record }))
+            })
+        });
+    let live = warp::path("live")
+        .and(warp::path::end())
+        .map(move || reply(Ok(Some(live_games.get()))));
+
+    warp::get().and(
+        leaderboard
+            .or(player)
+            .unify()
+            .or(match_record)
+            .unify()
+            .or(live)
+            .unify(),
+    )
+}
+
+// Storage calls block, so they run on tokio's blocking threads instead of holding up
+// every other request the HTTP server is handling.
+async fn read<T: Serialize + Send + 'static>(
+    persistence: Persistence,
+    read: impl FnOnce(&dyn Storage) -> Result<Option<T>, StorageError> + Send + 'static,
+) -> Result<WithStatus<Json>, Infallible> {
+    let result = tokio::task::spawn_blocking(move || read(&*persistence.storage())).await;
+    Ok(result.map_or_else(
+        |error| {
+            error!("Failed to read from storage: {error}");
+            unavailable()
+        },
+        reply,
+    ))
+}
+
+fn reply<T: Serialize>(result: Result<Option<T>, StorageError>) -> WithStatus<Json> {
+    match result {
+        Ok(Some(value)) => with_status(json(&value), StatusCode::OK),
+        Ok(None) => with_status(
+            json(&ApiError {
+                error: "Not found".to_string(),
+            }),
+            StatusCode::NOT_FOUND,
+        ),
+        Err(error) => {
+            error!("Failed to read from storage: {error}");
+            unavailable()
+        }
+    }
+}
+
+fn unavailable() -> WithStatus<Json> {
+    with_status(
+        json(&ApiError {
+            error: "Storage unavailable".to_string(),
+        }),
+        StatusCode::INTERNAL_SERVER_ERROR,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryStorage, Storage};
+    use game::{GameResult, PlayerSide};
+    use std::time::Duration;
+
+    fn get(
+        routes: &(impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + 'static),
+        path: &str,
+    ) -> (StatusCode, serde_json::Value) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let response =
+            runtime.block_on(warp::test::request().method("GET").path(path).reply(routes));
+        let body = serde_json::from_slice(response.body()).unwrap_or_default();
+        (response.status(), body)
+    }
+
+    #[test]
+    fn test_routes() {
+        let mut storage = MemoryStorage::default();
+        let alice = storage.create_player(b"alice", 100).unwrap();
+        let bob = storage.create_player(b"bob", 200).unwrap();
+        storage.set_rating(bob.id, Rating::new(1600.)).unwrap();
+        let record = MatchRecord {
+            left: Some(alice.id),
+            right: Some(bob.id),
+            arena_size: 7,
+            settings: GameSettings::default(),
+            result: GameResult {
+                winner: Some(PlayerSide::Right),
+                left_score: 1,
+                right_score: 3,
+                aborted: false,
+                forfeit: false,
+            },
+            duration: Duration::from_secs(90),
+            finished_at: 300,
+        };
+        let match_id = storage.record_match(&record).unwrap();
+        let live_games = LiveGames::default();
+        let routes = routes(Persistence::new(storage), live_games.clone());
+
+        let (status, leaderboard) = get(&routes, "/leaderboard?limit=1");
+        assert_eq!(status, StatusCode::OK);
+        let leaderboard: Vec<LeaderboardEntry> = serde_json::from_value(leaderboard).unwrap();
+        assert_eq!(
+            leaderboard,
+            vec![LeaderboardEntry {
+                rank: 1,
+                player: PlayerProfile {
+                    rating: Rating::new(1600.),
+                    ..bob
+                },
+            }]
+        );
+
+        let (status, player) = get(&routes, &format!("/players/{}", alice.id));
+        assert_eq!(status, StatusCode::OK);
+        let player: PlayerDetails = serde_json::from_value(player).unwrap();
+        assert_eq!(player.player, alice);
+        assert_eq!(
+            player.matches,
+            vec![MatchDetails {
+                id: match_id,
+                record: record.clone(),
+            }]
+        );
+        assert_eq!(get(&routes, "/players/99").0, StatusCode::NOT_FOUND);
+        assert_eq!(get(&routes, "/players/alice").0, StatusCode::NOT_FOUND);
+
+        let (status, details) = get(&routes, &format!("/matches/{match_id}"));
+        assert_eq!(status, StatusCode::OK);
+        let details: MatchDetails = serde_json::from_value(details).unwrap();
+        assert_eq!(details.record, record);
+
+        assert_eq!(get(&routes, "/live").1, serde_json::json!([]));
+        let game = LiveGame {
+            game: 42,
+            left: LivePlayer {
+                player: Some(alice.id),
+                rating: Some(Rating::default()),
+                is_bot: false,
+                score: 2,
+            },
+            right: LivePlayer {
+                player: None,
+                rating: None,
+                is_bot: true,
+                score: 1,
+            },
+            arena_size: 6,
+            settings: GameSettings::default(),
+            elapsed_secs: 12,
+        };
+        live_games.set(vec![game.clone()]);
+        let (_, live) = get(&routes, "/live");
+        assert_eq!(
+            serde_json::from_value::<Vec<LiveGame>>(live).unwrap(),
+            vec![game]
+        );
+    }
+}
@@ -0,0 +1,631 @@
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use thiserror::Error;
+
+use renet2::transport::NETCODE_KEY_BYTES;
+
+use game::{Dictionaries, DictionaryError, DictionaryName};
+
+use crate::{Persistence, QueueSettings, SqliteStorage, StorageError};
+
+// admin tokens are guessable below this length
+pub const MIN_ADMIN_TOKEN_LENGTH: usize = 16;
+// browsers refuse certificates pinned by their hash once they are valid for longer
+pub const MAX_CERT_LIFETIME_DAYS: u64 = 14;
+
+// Everything about the server that can change between deployments. Settings are read from a
+// TOML file (where every key is optional), then overridden by environment variables and finally
+// by command line flags, see `ServerConfig::load`.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    // the address both the game and token sockets listen on
+    pub bind_ip: IpAddr,
+    // the address clients reach the game server at, if not `bind_ip` (e.g. behind a proxy)
+    pub public_ip: Option<IpAddr>,
+    pub port: u16,
+    pub tokens_port: u16,
+    // Prometheus scrapes `/metrics` here, which players should not be able to reach
+    pub metrics_port: u16,
+    pub max_clients: usize,
+    // how long the game server's self-signed certificate is valid for. Web clients cannot
+    // connect once it runs out, so the server has to be restarted before then.
+    pub cert_lifetime_days: u64,
+    // base64 encoded 32 byte key that connect tokens are signed with. Without one, a random key
+    // is made up at startup, so tokens issued before a restart stop working.
+    pub private_key: Option<String>,
+    // web clients served from anywhere else cannot fetch a token
+    pub cors_origins: Vec<String>,
+    // the bearer token the admin API expects. Without one, the admin API is turned off.
+    pub admin_token: Option<String>,
+    // finished games are saved here as replays, if set
+    pub replay_directory: Option<PathBuf>,
+    // SQLite database that accounts and match history are kept in. Without one they are only
+    // kept in memory and lost on restart.
+    pub database: Option<PathBuf>,
+    // every `.txt` file in here is loaded as an extra dictionary named after the file
+    pub dictionary_directory: Option<PathBuf>,
+    // the dictionary matchmade games are played with, if not the default one
+    pub dictionary: Option<String>,
+    // how long a player waits for an opponent before being matched with a bot, or 0 for never
+    pub bot_wait_secs: u64,
+    // how long games in progress may go on once the server is asked to shut down
+    pub drain_secs: u64,
+    pub queue: QueueSettings,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            public_ip: None,
+            port: 7636,
+            tokens_port: 7637,
+            metrics_port: 7638,
+            max_clients: 64,
+            cert_lifetime_days: MAX_CERT_LIFETIME_DAYS,
+            private_key: None,
+            cors_origins: [
+                "http://localhost:8000",
+                "http://localhost:8080",
+                "http://127.0.0.1:8000",
+                "http://127.0.0.1:8080",
+                "https://wordfight.deno.dev",
+            ]
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+            admin_token: None,
+            replay_directory: None,
+            database: None,
+            dictionary_directory: None,
+            dictionary: None,
+            bot_wait_secs: 20,
+            drain_secs: 120,
+            queue: QueueSettings::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    // Reads the file at `path` (if any), applies `env` and then `cli` on top of it,
+    // and checks that the result makes sense.
+    pub fn load(
+        path: Option<&Path>,
+        env: ServerConfigOverrides,
+        cli: ServerConfigOverrides,
+    ) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply(env);
+        config.apply(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.into(),
+            source,
+        })?;
+        toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+            path: path.into(),
+            source,
+        })
+    }
+
+    pub fn apply(&mut self, overrides: ServerConfigOverrides) {
+        let ServerConfigOverrides {
+            bind_ip,
+            public_ip,
+            port,
+            tokens_port,
+            metrics_port,
+            max_clients,
+            cert_lifetime_days,
+            private_key,
+            cors_origins,
+            admin_token,
+            replay_directory,
+            database,
+            dictionary_directory,
+            dictionary,
+            bot_wait_secs,
+            drain_secs,
+        } = overrides;
+        self.bind_ip = bind_ip.unwrap_or(self.bind_ip);
+        if public_ip.is_some() {
+            self.public_ip = public_ip;
+        }
+        self.port = port.unwrap_or(self.port);
+        self.tokens_port = tokens_port.unwrap_or(self.tokens_port);
+        self.metrics_port = metrics_port.unwrap_or(self.metrics_port);
+        self.max_clients = max_clients.unwrap_or(self.max_clients);
+        self.cert_lifetime_days = cert_lifetime_days.unwrap_or(self.cert_lifetime_days);
+        if private_key.is_some() {
+            self.private_key = private_key;
+        }
+        if let Some(cors_origins) = cors_origins {
+            self.cors_origins = cors_origins;
+        }
+        if admin_token.is_some() {
+            self.admin_token = admin_token;
+        }
+        if replay_directory.is_some() {
+            self.replay_directory = replay_directory;
+        }
+        if database.is_some() {
+            self.database = database;
+        }
+        if dictionary_directory.is_some() {
+            self.dictionary_directory = dictionary_directory;
+        }
+        if dictionary.is_some() {
+            self.dictionary = dictionary;
+        }
+        self.bot_wait_secs = bot_wait_secs.unwrap_or(self.bot_wait_secs);
+        self.drain_secs = drain_secs.unwrap_or(self.drain_secs);
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let ports = [self.port, self.tokens_port, self.metrics_port];
+        if ports.contains(&0) {
+            return Err(ConfigError::UnassignedPort);
+        }
+        if let Some(port) = ports
+            .iter()
+            .enumerate()
+            .find_map(|(index, port)| ports[index + 1..].contains(port).then_some(*port))
+        {
+            return Err(ConfigError::SharedPort(port));
+        }
+        if self.max_clients == 0 {
+            return Err(ConfigError::NoClients);
+        }
+        if !(1..=MAX_CERT_LIFETIME_DAYS).contains(&self.cert_lifetime_days) {
+            return Err(ConfigError::InvalidCertLifetime(self.cert_lifetime_days));
+        }
+        self.private_key()?;
+        // origins are matched exactly, so anything like a trailing slash would never match
+        if let Some(origin) = self.cors_origins.iter().find(|origin| {
+            url::Url::parse(origin)
+                .map_or(true, |url| url.origin().ascii_serialization() != **origin)
+        }) {
+            return Err(ConfigError::InvalidOrigin(origin.clone()));
+        }
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.len() < MIN_ADMIN_TOKEN_LENGTH)
+        {
+            return Err(ConfigError::WeakAdminToken);
+        }
+        if let Some(directory) = &self.dictionary_directory {
+            if !directory.is_dir() {
+                return Err(ConfigError::MissingDictionaryDirectory(directory.clone()));
+            }
+        }
+        let queue = &self.queue;
+        if queue.initial_gap < 0. || queue.gap_growth < 0. || queue.max_gap < queue.initial_gap {
+            return Err(ConfigError::InvalidQueueSettings(*queue));
+        }
+        Ok(())
+    }
+
+    // The storage to keep accounts and match history in, and a read only connection to it
+    // for the HTTP API, or None to keep them in memory.
+    pub fn open_database(&self) -> Result<Option<(Persistence, Persistence)>, ConfigError> {
+        let Some(path) = &self.database else {
+            return Ok(None);
+        };
+        let error = |source| ConfigError::Database {
+            path: path.clone(),
+            source,
+        };
+        let storage = SqliteStorage::open(path).map_err(error)?;
+        let reader = SqliteStorage::open_read_only(path).map_err(error)?;
+        Ok(Some((Persistence::new(storage), Persistence::new(reader))))
+    }
+
+    // Creates the replay directory if it is missing, returning it if one is set.
+    pub fn create_replay_directory(&self) -> Result<Option<&Path>, ConfigError> {
+        let Some(path) = &self.replay_directory else {
+            return Ok(None);
+        };
+        std::fs::create_dir_all(path).map_err(|source| ConfigError::ReplayDirectory {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(Some(path))
+    }
+
+    // Every dictionary the server can use, along with the one matchmade games are played with.
+    pub fn load_dictionaries(&self) -> Result<(Dictionaries, DictionaryName), ConfigError> {
+        let mut dictionaries = Dictionaries::default();
+        if let Some(path) = &self.dictionary_directory {
+            dictionaries
+                .load_directory(path)
+                .map_err(|source| ConfigError::Dictionaries {
+                    path: path.clone(),
+                    source,
+                })?;
+        }
+        let dictionary = self
+            .dictionary
+            .clone()
+            .map(DictionaryName::new)
+            .unwrap_or_default();
+        if !dictionaries.contains(&dictionary) {
+            return Err(ConfigError::UnknownDictionary(dictionary));
+        }
+        Ok((dictionaries, dictionary))
+    }
+
+    pub fn public_ip(&self) -> IpAddr {
+        self.public_ip.unwrap_or(self.bind_ip)
+    }
+
+    pub fn private_key(&self) -> Result<Option<[u8; NETCODE_KEY_BYTES]>, ConfigError> {
+        let Some(private_key) = &self.private_key else {
+            return Ok(None);
+        };
+        BASE64_STANDARD
+            .decode(private_key.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Some)
+            .ok_or(ConfigError::InvalidPrivateKey)
+    }
+}
+
+// Settings given by environment variables or command line flags, which replace whatever the
+// config file says. The queue can only be tuned in the file.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(clap::Args)]
+pub struct ServerConfigOverrides {
+    /// Address to listen on [env: SERVER_BIND_IP]
+    #[arg(long)]
+    pub bind_ip: Option<IpAddr>,
+    /// Address clients reach the server at, if not the one it listens on [env: SERVER_PUBLIC_IP]
+    #[arg(long)]
+    pub public_ip: Option<IpAddr>,
+    /// Port clients connect to [env: SERVER_PORT]
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Port clients fetch their connection token from [env: SERVER_TOKENS_PORT]
+    #[arg(long)]
+    pub tokens_port: Option<u16>,
+    /// Port Prometheus scrapes `/metrics` from [env: SERVER_METRICS_PORT]
+    #[arg(long)]
+    pub metrics_port: Option<u16>,
+    /// Most clients connected at once [env: SERVER_MAX_CLIENTS]
+    #[arg(long)]
+    pub max_clients: Option<usize>,
+    /// Days the game server's certificate is valid for, at most 14 [env: SERVER_CERT_LIFETIME_DAYS]
+    #[arg(long)]
+    pub cert_lifetime_days: Option<u64>,
+    /// Base64 encoded 32 byte key to sign connect tokens with [env: SERVER_PRIVATE_KEY]
+    #[arg(long)]
+    pub private_key: Option<String>,
+    /// Origins web clients may be served from [env: SERVER_CORS_ORIGINS]
+    #[arg(long, value_delimiter = ',')]
+    pub cors_origins: Option<Vec<String>>,
+    /// Bearer token that turns on the admin API [env: SERVER_ADMIN_TOKEN]
+    #[arg(long)]
+    pub admin_token: Option<String>,
+    /// Directory to save replays of finished games to [env: SERVER_REPLAY_DIR]
+    #[arg(long)]
+    pub replay_directory: Option<PathBuf>,
+    /// SQLite database to keep accounts and match history in [env: SERVER_DATABASE]
+    #[arg(long)]
+    pub database: Option<PathBuf>,
+    /// Directory of extra word lists to load [env: SERVER_DICTIONARY_DIR]
+    #[arg(long)]
+    pub dictionary_directory: Option<PathBuf>,
+    /// Dictionary matchmade games are played with [env: SERVER_DICTIONARY]
+    #[arg(long)]
+    pub dictionary: Option<String>,
+    /// Seconds before a waiting player is matched with a bot, 0 for never [env: SERVER_BOT_WAIT_SECS]
+    #[arg(long)]
+    pub bot_wait_secs: Option<u64>,
+    /// Seconds games in progress get to finish when shutting down [env: SERVER_DRAIN_SECS]
+    #[arg(long)]
+    pub drain_secs: Option<u64>,
+}
+
+impl ServerConfigOverrides {
+    // Picks the server's settings out of environment `variables`, e.g. `std::env::vars()`.
+    // Anything else is ignored.
+    pub fn from_env(
+        variables: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut overrides = Self::default();
+        for (name, value) in variables {
+            match name.as_str() {
+                "SERVER_BIND_IP" => overrides.bind_ip = Some(parse_env(&name, &value)?),
+                "SERVER_PUBLIC_IP" => overrides.public_ip = Some(parse_env(&name, &value)?),
+                "SERVER_PORT" => overrides.port = Some(parse_env(&name, &value)?),
+                "SERVER_TOKENS_PORT" => overrides.tokens_port = Some(parse_env(&name, &value)?),
+                "SERVER_METRICS_PORT" => overrides.metrics_port = Some(parse_env(&name, &value)?),
+                "SERVER_MAX_CLIENTS" => overrides.max_clients = Some(parse_env(&name, &value)?),
+                "SERVER_CERT_LIFETIME_DAYS" => {
+                    overrides.cert_lifetime_days = Some(parse_env(&name, &value)?);
+                }
+                "SERVER_PRIVATE_KEY" => overrides.private_key = Some(value),
+                "SERVER_CORS_ORIGINS" => {
+                    overrides.cors_origins = Some(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|origin| !origin.is_empty())
+                            .map(str::to_string)
+                            .collect(),
+                    );
+                }
+                "SERVER_ADMIN_TOKEN" => overrides.admin_token = Some(value),
+                "SERVER_REPLAY_DIR" => overrides.replay_directory = Some(value.into()),
+                "SERVER_DATABASE" => overrides.database = Some(value.into()),
+                "SERVER_DICTIONARY_DIR" => overrides.dictionary_directory = Some(value.into()),
+                "SERVER_DICTIONARY" => overrides.dictionary = Some(value),
+                "SERVER_BOT_WAIT_SECS" => {
+                    overrides.bot_wait_secs = Some(parse_env(&name, &value)?);
+                }
+                "SERVER_DRAIN_SECS" => overrides.drain_secs = Some(parse_env(&name, &value)?),
+                _ => {}
+            }
+        }
+        Ok(overrides)
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str, value: &str) -> Result<T, ConfigError>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|error: T::Err| ConfigError::Env {
+            name: name.to_string(),
+            value: value.to_string(),
+            reason: error.to_string(),
+        })
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Environment variable {name}={value:?} is invalid: {reason}")]
+    Env {
+        name: String,
+        value: String,
+        reason: String,
+    },
+    #[error("Ports must be set to something other than 0")]
+    UnassignedPort,
+    #[error("The game, token and metrics servers each need their own port, but share {0}")]
+    SharedPort(u16),
+    #[error("The server must allow at least one client")]
+    NoClients,
+    #[error("The certificate must be valid for 1 to {MAX_CERT_LIFETIME_DAYS} days, not {0}")]
+    InvalidCertLifetime(u64),
+    #[error("The private key must be {NETCODE_KEY_BYTES} bytes, encoded as base64")]
+    InvalidPrivateKey,
+    #[error("CORS origin {0:?} should look like scheme://host[:port]")]
+    InvalidOrigin(String),
+    #[error("The admin token must be at least {MIN_ADMIN_TOKEN_LENGTH} characters long")]
+    WeakAdminToken,
+    #[error("Dictionary directory {0} does not exist")]
+    MissingDictionaryDirectory(PathBuf),
+    #[error("Failed to load dictionaries from {path}: {source}")]
+    Dictionaries {
+        path: PathBuf,
+        source: DictionaryError,
+    },
+    #[error("Dictionary {0} is not loaded, so games cannot be played with it")]
+    UnknownDictionary(DictionaryName),
+    #[error("Failed to open database {path}: {source}")]
+    Database { path: PathBuf, source: StorageError },
+    #[error("Failed to create replay directory {path}: {source}")]
+    ReplayDirectory {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error(
+        "Queue gaps must not be negative, and the max gap must be at least the initial gap: {0:?}"
+    )]
+    InvalidQueueSettings(QueueSettings),
+    #[error("Failed to generate the server's certificate: {0}")]
+    Certificate(rcgen::Error),
+    #[error("Failed to start the async runtime: {0}")]
+    Runtime(std::io::Error),
+    #[error("Failed to listen on {address}: {reason}")]
+    Bind { address: SocketAddr, reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ServerSockets;
+
+    fn env(variables: &[(&str, &str)]) -> Result<ServerConfigOverrides, ConfigError> {
+        ServerConfigOverrides::from_env(
+            variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        )
+    }
+
+    #[test]
+    fn test_config_precedence() {
+        let path =
+            std::env::temp_dir().join(format!("wordfight-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "port = 9000\ntokens_port = 9001\nmax_clients = 8\n\n[queue]\ninitial_gap = 50\n",
+        )
+        .unwrap();
+
+        let overrides = env(&[
+            ("SERVER_TOKENS_PORT", "9002"),
+            ("SERVER_MAX_CLIENTS", "16"),
+            ("SERVER_CERT_LIFETIME_DAYS", "7"),
+            (
+                "SERVER_CORS_ORIGINS",
+                "https://example.com, http://localhost:3000",
+            ),
+            ("UNRELATED", "ignored"),
+        ])
+        .unwrap();
+        let cli = ServerConfigOverrides {
+            max_clients: Some(32),
+            ..Default::default()
+        };
+        let config = ServerConfig::load(Some(&path), overrides, cli).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.tokens_port, 9002);
+        assert_eq!(config.max_clients, 32);
+        assert_eq!(config.cert_lifetime_days, 7);
+        assert_eq!(
+            config.cors_origins,
+            vec!["https://example.com", "http://localhost:3000"]
+        );
+        assert_eq!(config.queue.initial_gap, 50.);
+        assert_eq!(config.queue.max_gap, QueueSettings::default().max_gap);
+        assert_eq!(config.bot_wait_secs, ServerConfig::default().bot_wait_secs);
+        assert_eq!(config.private_key().unwrap(), None);
+        assert_eq!(config.public_ip(), config.bind_ip);
+
+        let key = [7; NETCODE_KEY_BYTES];
+        let config = ServerConfig::load(
+            None,
+            env(&[("SERVER_PRIVATE_KEY", &BASE64_STANDARD.encode(key))]).unwrap(),
+            ServerConfigOverrides::default(),
+        )
+        .unwrap();
+        assert_eq!(config.private_key().unwrap(), Some(key));
+    }
+
+    #[test]
+    fn test_config_errors() {
+        assert!(matches!(
+            env(&[("SERVER_PORT", "lots")]),
+            Err(ConfigError::Env { name, .. }) if name == "SERVER_PORT"
+        ));
+        // typos are caught rather than silently ignored
+        assert!(toml::from_str::<ServerConfig>("prot = 9000").is_err());
+
+        let load = |cli| ServerConfig::load(None, ServerConfigOverrides::default(), cli);
+        assert!(load(ServerConfigOverrides::default()).is_ok());
+        assert!(matches!(
+            load(ServerConfigOverrides {
+                tokens_port: Some(7636),
+                ..Default::default()
+            }),
+            Err(ConfigError::SharedPort(7636))
+        ));
+        assert!(matches!(
+            load(ServerConfigOverrides {
+                metrics_port: Some(7637),
+                ..Default::default()
+            }),
+            Err(ConfigError::SharedPort(7637))
+        ));
+        assert!(matches!(
+            load(ServerConfigOverrides {
+                max_clients: Some(0),
+                ..Default::default()
+            }),
+            Err(ConfigError::NoClients)
+        ));
+        assert!(matches!(
+            load(ServerConfigOverrides {
+                cert_lifetime_days: Some(30),
+                ..Default::default()
+            }),
+            Err(ConfigError::InvalidCertLifetime(30))
+        ));
+        assert!(matches!(
+            load(ServerConfigOverrides {
+                private_key: Some(BASE64_STANDARD.encode([7; 16])),
+                ..Default::default()
+            }),
+            Err(ConfigError::InvalidPrivateKey)
+        ));
+        assert!(matches!(
+            load(ServerConfigOverrides {
+                admin_token: Some("hunter2".to_string()),
+                ..Default::default()
+            }),
+            Err(ConfigError::WeakAdminToken)
+        ));
+        assert!(matches!(
+            load(ServerConfigOverrides {
+                cors_origins: Some(vec!["localhost".to_string()]),
+                ..Default::default()
+            }),
+            Err(ConfigError::InvalidOrigin(_))
+        ));
+        assert!(matches!(
+            load(ServerConfigOverrides {
+                cors_origins: Some(vec!["https://example.com/".to_string()]),
+                ..Default::default()
+            }),
+            Err(ConfigError::InvalidOrigin(_))
+        ));
+    }
+
+    #[test]
+    fn test_setup_errors() {
+        let config = ServerConfig {
+            dictionary: Some("klingon".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.load_dictionaries(),
+            Err(ConfigError::UnknownDictionary(name)) if *name == "klingon"
+        ));
+
+        let missing =
+            std::env::temp_dir().join(format!("wordfight-missing-{}", std::process::id()));
+        let config = ServerConfig {
+            database: Some(missing.join("wordfight.db")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.open_database(),
+            Err(ConfigError::Database { .. })
+        ));
+
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = ServerConfig {
+            bind_ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            tokens_port: taken.local_addr().unwrap().port(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ServerSockets::bind(&config, [7; NETCODE_KEY_BYTES]),
+            Err(ConfigError::Bind { address, .. }) if address == taken.local_addr().unwrap()
+        ));
+    }
+}
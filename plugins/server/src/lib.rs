@@ -1,21 +1,55 @@
 use rand_core::RngCore;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
 
 use bevy::{
-    log::info,
+    log::{error, info, warn},
     prelude::{
-        App, Commands, Entity, EventReader, IntoSystemConfigs, Plugin, Query, Res, ResMut, Startup,
-        Update, With, Without,
+        not, resource_exists, App, AppExit, Commands, Component, Deref, DerefMut, Entity,
+        EventReader, EventWriter, Has, IntoSystemConfigs, OnAdd, Plugin, Query, Res, ResMut,
+        Resource, Startup, Time, Timer, TimerMode, Trigger, Update, With, Without, World,
     },
 };
 use bevy_prng::WyRand;
 use bevy_rand::prelude::{EntropyPlugin as RandEntropyPlugin, *};
-use bevy_replicon::prelude::{ConnectedClients, RepliconChannels, ServerEvent};
+use bevy_replicon::prelude::{
+    ConnectedClients, FromClient, RepliconChannels, SendMode, ServerEvent, ToClients,
+};
 use bevy_replicon_renet2::{
-    renet2::{ConnectionConfig, RenetServer},
+    renet2::{
+        transport::{generate_random_bytes, NetcodeServerTransport},
+        ConnectionConfig, RenetServer,
+    },
     RenetChannelsExt, RepliconRenetServerPlugin,
 };
 
-use game::{Client, InGame, SpawnGame};
+use bot_controller::{BotController, BotControllerPlugin};
+use game::{
+    Arena, Bot, Client, ClientEntities, Dictionaries, DictionaryName, Game, GameEnded, GamePlayers,
+    GameResult, GameSettings, GameSettingsQuery, InGame, InRoom, PlayerBundle, PlayerId,
+    PlayerSide, Rating, ReplayDirectory, RoomCode, RoomRequest, RoomResponse, Score,
+    ServerShutdown, SpawnGame, Welcome,
+};
+
+mod account;
+pub use account::*;
+mod admin;
+pub use admin::*;
+mod api;
+pub use api::*;
+mod config;
+pub use config::*;
+mod metrics;
+pub use metrics::*;
+mod queue;
+pub use queue::*;
+mod shutdown;
+pub use shutdown::*;
+mod storage;
+pub use storage::*;
 
 mod transport;
 use transport::*;
@@ -23,28 +57,152 @@ use transport::*;
 pub type Entropy = EntropyComponent<WyRand>;
 pub type GlobalEntropy = bevy_rand::prelude::GlobalEntropy<WyRand>;
 
+// clients that are not currently playing
+type InLobby = (With<Client>, Without<InGame>);
+type VisibilityDetails<'a> = (Entity, &'a Client, Option<&'a InGame>, Option<&'a InRoom>);
+
+// everything about a game that its `MatchRecord` keeps
+type GameDetails<'a> = (
+    &'a GamePlayers,
+    &'a Arena,
+    GameSettingsQuery,
+    &'a MatchStart,
+);
+
+// what `/live` shows of each player
+type LivePlayerDetails<'a> = (
+    &'a Score,
+    Option<&'a PlayerId>,
+    Option<&'a Rating>,
+    Has<Bot>,
+);
+
+// how long players stay in a finished game (to see the result) before being matched again
+const POST_GAME_DURATION: Duration = Duration::from_secs(5);
+
 pub struct ServerPlugin {
-    pub port: String,
-    pub wt_tokens_port: String,
+    config: ServerConfig,
+    persistence: Persistence,
+    // a separate connection for the HTTP API, so its reads do not queue up behind the server
+    api_persistence: Persistence,
+    // handed over to the app when the plugin is built
+    dictionaries: Mutex<Option<Dictionaries>>,
+    dictionary: DictionaryName,
+    sockets: Mutex<Option<ServerSockets>>,
+}
+
+impl ServerPlugin {
+    // Sets up everything the config points at (the database, replay directory, dictionaries and
+    // ports), so that a bad config stops the server before it starts rather than once it is running.
+    pub fn new(config: ServerConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let private_key = config.private_key()?.unwrap_or_else(generate_random_bytes);
+        let (persistence, api_persistence) = config.open_database()?.unwrap_or_else(|| {
+            let persistence = Persistence::new(MemoryStorage::default());
+            (persistence.clone(), persistence)
+        });
+        config.create_replay_directory()?;
+        let (dictionaries, dictionary) = config.load_dictionaries()?;
+        let sockets = ServerSockets::bind(&config, private_key)?;
+        Ok(Self {
+            config,
+            persistence,
+            api_persistence,
+            dictionaries: Mutex::new(Some(dictionaries)),
+            dictionary,
+            sockets: Mutex::new(Some(sockets)),
+        })
+    }
 }
 
 impl Plugin for ServerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((
-            RepliconRenetServerPlugin,
-            RandEntropyPlugin::<WyRand>::default(),
-        ));
+        let config = &self.config;
+        app.add_plugins(RepliconRenetServerPlugin);
+        // bots may have set up entropy already
+        if !app.is_plugin_added::<RandEntropyPlugin<WyRand>>() {
+            app.add_plugins(RandEntropyPlugin::<WyRand>::default());
+        }
 
+        if config.private_key.is_none() {
+            warn!("No private key configured, so connect tokens are signed with a random one");
+        }
+        if config.database.is_none() {
+            warn!("No database configured, so accounts and match history are lost on exit");
+        }
+        let live_games = LiveGames::default();
+        let (admin_requests, admin_receiver) = AdminRequests::new();
+        let metrics = ServerMetrics::default();
+        let shutdown = ShutdownSignal::listen().unwrap_or_else(|error| {
+            warn!("Failed to listen for shutdown signals, so the server cannot drain: {error}");
+            ShutdownSignal::default()
+        });
+        app.insert_resource(self.persistence.clone());
+        app.insert_resource(live_games.clone());
+        app.insert_resource(admin_receiver);
+        app.init_resource::<Matchmaking>();
+        app.insert_resource(shutdown.clone());
+        app.insert_resource(DrainTimeout(Duration::from_secs(config.drain_secs)));
+        app.add_plugins(ServerMetricsPlugin {
+            metrics: metrics.clone(),
+        });
         app.add_plugins(ServerTransportPlugin {
-            port: self.port.clone(),
-            wt_tokens_port: self.wt_tokens_port.clone(),
+            sockets: Mutex::new(
+                self.sockets
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take(),
+            ),
+            cors_origins: config.cors_origins.clone(),
+            persistence: self.api_persistence.clone(),
+            live_games,
+            admin_token: config.admin_token.clone(),
+            admin_requests,
+            metrics,
+            shutdown,
         });
+        if let Some(replay_directory) = &config.replay_directory {
+            app.insert_resource(ReplayDirectory(replay_directory.clone()));
+        }
+        if let Some(dictionaries) = self
+            .dictionaries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            info!(
+                "Loaded dictionaries: {:?}",
+                dictionaries.names().collect::<Vec<_>>()
+            );
+            app.insert_resource(dictionaries);
+        }
+        app.insert_resource(MatchDictionary(self.dictionary.clone()));
+        app.insert_resource(MatchQueue::new(config.queue));
+        if config.bot_wait_secs > 0 {
+            if !app.is_plugin_added::<BotControllerPlugin>() {
+                app.add_plugins(BotControllerPlugin);
+            }
+            app.insert_resource(BotWait(Duration::from_secs(config.bot_wait_secs)));
+        }
+        app.observe(Self::start_match_clock);
         app.add_systems(Startup, Self::start_server).add_systems(
             Update,
             (
+                Self::handle_admin_requests,
+                Self::start_draining,
                 Self::handle_connections,
-                Self::matchmake,
+                Self::handle_game_ended,
+                Self::record_matches,
+                Self::return_players_to_lobby,
+                Self::handle_room_requests,
+                Self::match_rooms.run_if(not(resource_exists::<Draining>)),
+                Self::despawn_idle_bots,
+                Self::matchmake
+                    .run_if(matchmaking_enabled)
+                    .run_if(not(resource_exists::<Draining>)),
                 Self::handle_visibility,
+                Self::update_live_games,
+                Self::drain.run_if(resource_exists::<Draining>),
             )
                 .chain(),
         );
@@ -69,41 +227,407 @@ impl ServerPlugin {
         commands.insert_resource(server);
     }
 
+    // each request is carried out with full access to the world once commands are applied
+    fn handle_admin_requests(mut commands: Commands, requests: Res<AdminRequests>) {
+        for request in requests.drain() {
+            commands.add(request);
+        }
+    }
+
+    // no one new is let in (or matched) once the server has been asked to shut down
+    fn start_draining(
+        mut commands: Commands,
+        signal: Res<ShutdownSignal>,
+        timeout: Res<DrainTimeout>,
+        draining: Option<Res<Draining>>,
+        mut shutdowns: EventWriter<ToClients<ServerShutdown>>,
+    ) {
+        if draining.is_some() || !signal.is_raised() {
+            return;
+        }
+        info!(
+            "Shutting down, after giving games in progress up to {}s to finish",
+            timeout.as_secs()
+        );
+        commands.insert_resource(Draining::new(**timeout));
+        shutdowns.send(ToClients {
+            mode: SendMode::Broadcast,
+            event: ServerShutdown {
+                deadline_secs: timeout.as_secs(),
+            },
+        });
+    }
+
+    // Games still being played once the deadline has passed are called off without rating them.
+    // Once every game has been recorded and its players have left it, the server exits.
+    fn drain(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut draining: ResMut<Draining>,
+        games: Query<(Entity, Has<GameResult>), With<Game>>,
+        mut exit: EventWriter<AppExit>,
+    ) {
+        if games.is_empty() {
+            info!("Every game is over, shutting down");
+            commands.add(|world: &mut World| {
+                if let Some(mut server) = world.get_resource_mut::<RenetServer>() {
+                    server.disconnect_all();
+                }
+            });
+            exit.send(AppExit::Success);
+            return;
+        }
+        // checked every frame, so nothing that started after the deadline keeps the server up
+        if !draining.tick(time.delta()).finished() {
+            return;
+        }
+        for (game, is_finished) in &games {
+            if !is_finished {
+                warn!("Game {game}: Calling it off unrated, since the server is shutting down");
+                commands.add(move |world: &mut World| {
+                    end_game(world, game);
+                });
+            }
+        }
+    }
+
     fn matchmake(
         mut commands: Commands,
-        clients: Query<Entity, (With<Client>, Without<InGame>)>,
+        time: Res<Time>,
+        clients: Query<(Entity, &Rating), (InLobby, Without<InRoom>)>,
+        mut queue: ResMut<MatchQueue>,
         mut entropy: ResMut<GlobalEntropy>,
+        dictionary: Res<MatchDictionary>,
+        bot_wait: Option<Res<BotWait>>,
     ) {
-        for [client1, client2] in clients
-            .iter()
-            .collect::<Vec<_>>()
-            .chunks_exact(2)
-            .map(|chunk| [chunk[0], chunk[1]])
-        {
-            info!("Found match: {client1} + {client2}");
-            let arena_size = 6 + (entropy.next_u64() / (u64::MAX / 3)) as usize;
-            commands.trigger(SpawnGame::new(arena_size, client1, client2));
+        // players leave the queue when they disconnect, join a room or somehow end up in a game
+        queue.retain(|player| clients.contains(player));
+        for (client, rating) in &clients {
+            queue.join(client, *rating);
+        }
+        queue.tick(time.delta());
+
+        let mut matches = queue.take_matches();
+        if let Some(bot_wait) = bot_wait {
+            for client in queue.take_waited(bot_wait.0) {
+                let bot = commands.spawn(BotController::default().bundle()).id();
+                info!("Found no match for {client} in time, matching with bot {bot}");
+                matches.push([client, bot]);
+            }
+        }
+        for [player1, player2] in matches {
+            info!("Found match: {player1} + {player2}");
+            commands.trigger(
+                SpawnGame::new(random_arena_size(&mut entropy), player1, player2)
+                    .with_settings(GameSettings::default().with_dictionary(dictionary.0.clone())),
+            );
+        }
+    }
+
+    fn handle_room_requests(
+        mut commands: Commands,
+        mut requests: EventReader<FromClient<RoomRequest>>,
+        mut responses: EventWriter<ToClients<RoomResponse>>,
+        clients: Query<(Entity, &Client, Option<&InRoom>, Has<InGame>)>,
+        mut entropy: ResMut<GlobalEntropy>,
+    ) {
+        // room membership is tracked here as well, since the commands below only apply later
+        // and a single frame can hold several requests for the same room
+        let mut rooms = HashMap::<RoomCode, Vec<Entity>>::new();
+        for (client, _, in_room, _) in &clients {
+            if let Some(in_room) = in_room {
+                rooms.entry((**in_room).clone()).or_default().push(client);
+            }
+        }
+
+        for FromClient { client_id, event } in requests.read() {
+            let Some((client, _, _, in_game)) = clients
+                .iter()
+                .find(|(_, client, ..)| ***client == *client_id)
+            else {
+                continue;
+            };
+            let response = match event {
+                RoomRequest::Create | RoomRequest::Join(_) if in_game => RoomResponse::InGame,
+                RoomRequest::Create => {
+                    let code = loop {
+                        let code = RoomCode::from_random(entropy.next_u64());
+                        if !rooms.contains_key(&code) {
+                            break code;
+                        }
+                    };
+                    leave_room(&mut rooms, client);
+                    rooms.insert(code.clone(), vec![client]);
+                    commands.entity(client).insert(InRoom::new(code.clone()));
+                    info!("Client {client} created room {code}");
+                    RoomResponse::Created(code)
+                }
+                RoomRequest::Join(code) => match rooms.get(code) {
+                    None => RoomResponse::NotFound(code.clone()),
+                    Some(members) if members.contains(&client) => {
+                        RoomResponse::Joined(code.clone())
+                    }
+                    Some(members) if members.len() >= 2 => RoomResponse::Full(code.clone()),
+                    Some(_) => {
+                        leave_room(&mut rooms, client);
+                        rooms.entry(code.clone()).or_default().push(client);
+                        commands.entity(client).insert(InRoom::new(code.clone()));
+                        info!("Client {client} joined room {code}");
+                        RoomResponse::Joined(code.clone())
+                    }
+                },
+                RoomRequest::Leave => {
+                    leave_room(&mut rooms, client);
+                    commands.entity(client).remove::<InRoom>();
+                    RoomResponse::Left
+                }
+            };
+            responses.send(ToClients {
+                mode: SendMode::Direct(*client_id),
+                event: response,
+            });
+        }
+    }
+
+    // room members only ever play each other, and play again whenever both are free
+    fn match_rooms(
+        mut commands: Commands,
+        members: Query<(Entity, &InRoom), InLobby>,
+        mut entropy: ResMut<GlobalEntropy>,
+        dictionary: Res<MatchDictionary>,
+    ) {
+        let mut rooms = HashMap::<&RoomCode, Vec<Entity>>::new();
+        for (client, in_room) in &members {
+            rooms.entry(&**in_room).or_default().push(client);
+        }
+        for (code, members) in rooms {
+            if let [player1, player2] = members[..] {
+                info!("Room {code}: starting a game for {player1} + {player2}");
+                commands.trigger(
+                    SpawnGame::new(random_arena_size(&mut entropy), player1, player2)
+                        .with_settings(
+                            GameSettings::default().with_dictionary(dictionary.0.clone()),
+                        ),
+                );
+            }
+        }
+    }
+
+    // bots only exist to play a single game, so they leave with it
+    fn despawn_idle_bots(
+        mut commands: Commands,
+        bots: Query<(Entity, Option<&InGame>), With<Bot>>,
+        games: Query<(), With<Game>>,
+    ) {
+        for (bot, in_game) in &bots {
+            if !in_game.is_some_and(|in_game| games.contains(**in_game)) {
+                info!("Despawning bot {bot} after its game");
+                commands.entity(bot).despawn();
+            }
+        }
+    }
+
+    // only games between two clients are rated, since bots have no rating of their own,
+    // and games the server called off are not rated at all
+    fn handle_game_ended(
+        mut commands: Commands,
+        mut game_ended: EventReader<GameEnded>,
+        games: Query<&GamePlayers>,
+        mut ratings: Query<&mut Rating, With<Client>>,
+    ) {
+        for GameEnded { game, result } in game_ended.read() {
+            info!("Game {game} ended: {result:?}");
+            let players = games.get(*game).ok().filter(|_| !result.aborted);
+            if let Some(players) = players {
+                if let Ok([mut left, mut right]) =
+                    ratings.get_many_mut([players.left, players.right])
+                {
+                    Rating::update_game(&mut left, &mut right, result);
+                    info!("Game {game}: ratings are now {} and {}", *left, *right);
+                }
+            }
+            if let Some(mut game) = commands.get_entity(*game) {
+                game.insert(PostGameTimer(Timer::new(
+                    POST_GAME_DURATION,
+                    TimerMode::Once,
+                )));
+            }
+        }
+    }
+
+    fn start_match_clock(trigger: Trigger<OnAdd, Game>, mut commands: Commands) {
+        commands
+            .entity(trigger.entity())
+            .insert(MatchStart(SystemTime::now()));
+    }
+
+    // saves each finished game, along with the new ratings of its players
+    fn record_matches(
+        mut game_ended: EventReader<GameEnded>,
+        games: Query<GameDetails>,
+        players: Query<(&PlayerId, &Rating), With<Client>>,
+        persistence: Res<Persistence>,
+    ) {
+        for GameEnded { game, result } in game_ended.read() {
+            let Ok((game_players, arena, settings, start)) = games.get(*game) else {
+                continue;
+            };
+            let left = players.get(game_players.left).ok();
+            let right = players.get(game_players.right).ok();
+            let mut storage = persistence.storage();
+            for (player, rating) in left.iter().chain(right.iter()) {
+                if let Err(error) = storage.set_rating(**player, **rating) {
+                    error!("Failed to save the rating of player {}: {error}", **player);
+                }
+            }
+            let now = SystemTime::now();
+            let record = MatchRecord {
+                left: left.map(|(player, _)| *player),
+                right: right.map(|(player, _)| *player),
+                arena_size: arena.size(),
+                settings: settings.settings(),
+                result: *result,
+                duration: now.duration_since(**start).unwrap_or_default(),
+                finished_at: unix_time(now),
+            };
+            match storage.record_match(&record) {
+                Ok(id) => info!("Game {game}: saved as match {id}"),
+                Err(error) => error!("Failed to save game {game}: {error}"),
+            }
+        }
+    }
+
+    // lets the HTTP API list the games in progress
+    fn update_live_games(
+        games: Query<(Entity, GameDetails), Without<GameResult>>,
+        players: Query<LivePlayerDetails>,
+        live_games: Res<LiveGames>,
+    ) {
+        let now = SystemTime::now();
+        let live_player = |player| {
+            let (score, id, rating, is_bot) = players.get(player).ok()?;
+            Some(LivePlayer {
+                player: id.copied(),
+                // bots carry no rating of their own
+                rating: rating.filter(|_| !is_bot).copied(),
+                is_bot,
+                score: **score,
+            })
+        };
+        let mut live = Vec::new();
+        for (game, (game_players, arena, settings, start)) in &games {
+            // players only get a score once the game has set them up
+            let (Some(left), Some(right)) = (
+                live_player(game_players.left),
+                live_player(game_players.right),
+            ) else {
+                continue;
+            };
+            live.push(LiveGame {
+                game: game.to_bits(),
+                left,
+                right,
+                arena_size: arena.size(),
+                settings: settings.settings(),
+                elapsed_secs: now.duration_since(**start).unwrap_or_default().as_secs(),
+            });
+        }
+        live_games.set(live);
+    }
+
+    fn return_players_to_lobby(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut games: Query<(Entity, &GamePlayers, &mut PostGameTimer)>,
+        disconnected: Query<(), With<Disconnected>>,
+    ) {
+        for (game, players, mut timer) in &mut games {
+            if !timer.tick(time.delta()).just_finished() {
+                continue;
+            }
+            info!("Game {game}: Returning players to the lobby");
+            // the game despawns itself once it has no players left
+            for player in [players.left, players.right] {
+                let Some(mut player) = commands.get_entity(player) else {
+                    continue;
+                };
+                if disconnected.contains(player.id()) {
+                    player.despawn();
+                } else {
+                    player.remove::<PlayerBundle>();
+                }
+            }
         }
     }
 
     fn handle_connections(
         mut commands: Commands,
         mut server_events: EventReader<ServerEvent>,
-        clients: Query<(Entity, &Client)>,
+        mut welcomes: EventWriter<ToClients<Welcome>>,
+        clients: Res<ClientEntities>,
+        transport: Option<Res<NetcodeServerTransport>>,
+        persistence: Res<Persistence>,
+        draining: Option<Res<Draining>>,
     ) {
         for event in server_events.read() {
             match event {
                 ServerEvent::ClientConnected { client_id } => {
+                    // they may have fetched their token just before the server started draining
+                    if draining.is_some() {
+                        info!("Refusing player {} while shutting down", client_id.get());
+                        let client_id = client_id.get();
+                        commands.add(move |world: &mut World| disconnect(world, client_id));
+                        continue;
+                    }
                     info!("Player {} connected.", client_id.get());
+                    let key = transport
+                        .as_ref()
+                        .and_then(|transport| transport.user_data(client_id.get()))
+                        .and_then(|user_data| key_from_user_data(&user_data));
+                    let mut storage = persistence.storage();
+                    let signed_in = sign_in(&mut *storage, key, unix_time(SystemTime::now()));
+                    // bans are checked when tokens are handed out, but a token fetched just
+                    // before its player was banned is still good for a while
+                    if let Ok((profile, _)) = &signed_in {
+                        let banned = storage.is_banned(profile.id).unwrap_or_else(|error| {
+                            error!("Failed to check whether {} is banned: {error}", profile.id);
+                            false
+                        });
+                        if banned {
+                            info!("Refusing banned player {}", profile.id);
+                            let client_id = client_id.get();
+                            commands.add(move |world: &mut World| disconnect(world, client_id));
+                            continue;
+                        }
+                    }
+                    drop(storage);
                     // Spawn new player entity
-                    commands.spawn(Client::from(*client_id).bundle());
+                    let mut player =
+                        commands.spawn((Client::from(*client_id).bundle(), Rating::default()));
+                    match signed_in {
+                        Ok((profile, key)) => {
+                            info!("Player {} is signed in as {}", client_id.get(), profile.id);
+                            player.insert((profile.id, profile.rating));
+                            welcomes.send(ToClients {
+                                mode: SendMode::Direct(*client_id),
+                                event: Welcome {
+                                    player: profile.id,
+                                    name: profile.name,
+                                    rating: profile.rating,
+                                    key: encode_key(&key),
+                                },
+                            });
+                        }
+                        // they can still play, their games just are not kept
+                        Err(error) => {
+                            error!("Failed to sign in player {}: {error}", client_id.get());
+                        }
+                    }
                 }
                 ServerEvent::ClientDisconnected { client_id, reason } => {
-                    if let Some((player_entity, _)) =
-                        clients.iter().find(|(_, id)| ***id == *client_id)
-                    {
+                    if let Some(&player_entity) = clients.get(client_id) {
                         info!("Player disconnected: {}", reason);
-                        commands.entity(player_entity).despawn();
+                        commands.add(move |world: &mut World| leave_game(world, player_entity));
                     }
                 }
             }
@@ -111,20 +635,20 @@ impl ServerPlugin {
     }
 
     pub fn handle_visibility(
-        players: Query<(Entity, &Client, Option<&InGame>)>,
+        players: Query<VisibilityDetails, Without<Disconnected>>,
         game_entities: Query<(Entity, &InGame), Without<Client>>,
         mut connected_clients: ResMut<ConnectedClients>,
     ) {
         // let players have visibility over all entities present in the same game
         // and the games too
-        for (entity, player, player_game) in players
-            .iter()
-            .filter_map(|(entity, player, in_game)| in_game.map(|game| (entity, player, game)))
-        {
+        for (entity, player, in_game, _) in players.iter() {
+            // player can always see themselves
             let client = connected_clients.client_mut(**player);
             let visibility = client.visibility_mut();
-            // player can see themselves
             visibility.set_visibility(entity, true);
+            let Some(player_game) = in_game else {
+                continue;
+            };
             // and the game instance
             // TODO: turning this off when switching / ending games?
             visibility.set_visibility(**player_game, true);
@@ -139,12 +663,13 @@ impl ServerPlugin {
             }
         }
 
-        // players also need to be able to see each other when either both in lobby, or both in the same game
-        for [(entity1, player1, in_game1), (entity2, player2, in_game2)] in
+        // players also need to be able to see each other when either both in lobby (and in the
+        // same room, if any), or both in the same game
+        for [(entity1, player1, in_game1, in_room1), (entity2, player2, in_game2, in_room2)] in
             players.iter_combinations()
         {
             let visible = match (in_game1, in_game2) {
-                (None, None) => true,
+                (None, None) => in_room1 == in_room2,
                 (None, Some(_)) | (Some(_), None) => false,
                 (Some(game1), Some(game2)) => **game1 == **game2,
             };
@@ -158,3 +683,139 @@ impl ServerPlugin {
         }
     }
 }
+
+fn matchmaking_enabled(matchmaking: Res<Matchmaking>) -> bool {
+    matchmaking.enabled
+}
+
+fn random_arena_size(entropy: &mut GlobalEntropy) -> usize {
+    6 + (entropy.next_u64() / (u64::MAX / 3)) as usize
+}
+
+// Players who leave a game before it is decided forfeit it to their opponent. They are kept
+// (without a connection) until the game has been rated and recorded, and leave with it.
+fn leave_game(world: &mut World, player: Entity) {
+    let mut games = world.query_filtered::<(&GamePlayers, Has<GameResult>), With<Game>>();
+    let in_game = world.get::<InGame>(player).and_then(|in_game| {
+        let (players, is_finished) = games.get(world, **in_game).ok()?;
+        Some((**in_game, players.clone(), is_finished))
+    });
+    let Some((game, players, is_finished)) = in_game else {
+        world.despawn(player);
+        return;
+    };
+    world.entity_mut(player).insert(Disconnected);
+    if is_finished {
+        return;
+    }
+    let winner = if player == players.left {
+        PlayerSide::Right
+    } else {
+        PlayerSide::Left
+    };
+    let score = |player| world.get::<Score>(player).map_or(0, |score| **score);
+    let result = GameResult::forfeit(winner, score(players.left), score(players.right));
+    info!("Game {game}: {winner:?} wins, since the other player left");
+    world.entity_mut(game).insert(result);
+    world.send_event(GameEnded { game, result });
+}
+
+fn leave_room(rooms: &mut HashMap<RoomCode, Vec<Entity>>, client: Entity) {
+    rooms.retain(|_, members| {
+        members.retain(|member| *member != client);
+        !members.is_empty()
+    });
+}
+
+#[derive(Component, Deref, DerefMut)]
+struct PostGameTimer(Timer);
+
+// A client that left in the middle of a game. It is kept until the game has been recorded.
+#[derive(Component)]
+pub struct Disconnected;
+
+#[derive(Component, Deref)]
+struct MatchStart(SystemTime);
+
+#[derive(Resource)]
+struct BotWait(Duration);
+
+#[derive(Resource)]
+struct MatchDictionary(DictionaryName);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::GameBundle;
+
+    use bevy::{prelude::Virtual, time::TimeUpdateStrategy, MinimalPlugins};
+    use bevy_replicon::prelude::ClientId;
+
+    #[test]
+    fn test_forfeit() {
+        let mut storage = MemoryStorage::default();
+        let stayed = storage.create_player(b"stayed", 0).unwrap();
+        let quit = storage.create_player(b"quit", 0).unwrap();
+        let persistence = Persistence::new(storage);
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(POST_GAME_DURATION))
+            .add_event::<GameEnded>()
+            .insert_resource(persistence.clone())
+            .add_systems(
+                Update,
+                (
+                    ServerPlugin::handle_game_ended,
+                    ServerPlugin::record_matches,
+                    ServerPlugin::return_players_to_lobby,
+                )
+                    .chain(),
+            );
+        app.world_mut()
+            .resource_mut::<Time<Virtual>>()
+            .set_max_delta(POST_GAME_DURATION);
+        let mut player = |client, profile: &PlayerProfile| {
+            let client = Client::from(ClientId::new(client));
+            app.world_mut()
+                .spawn((client, profile.id, profile.rating))
+                .id()
+        };
+        let left = player(1, &stayed);
+        let right = player(2, &quit);
+        let game = app
+            .world_mut()
+            .spawn((
+                GameBundle::new(left, right, 6),
+                MatchStart(SystemTime::now()),
+            ))
+            .id();
+        for (player, side) in [(left, PlayerSide::Left), (right, PlayerSide::Right)] {
+            let bundle = PlayerBundle::new(side, game);
+            app.world_mut().entity_mut(player).insert(bundle);
+        }
+
+        // leaving mid-game forfeits it, which is rated and recorded like any other result
+        leave_game(app.world_mut(), right);
+        app.update();
+        let result = GameResult::forfeit(PlayerSide::Left, 0, 0);
+        assert_eq!(app.world().get::<GameResult>(game), Some(&result));
+        assert!(*app.world().get::<Rating>(left).unwrap() > Rating::default());
+        let matches = persistence.storage().player_matches(quit.id, 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].1.result, result);
+        assert!(
+            persistence
+                .storage()
+                .player(quit.id)
+                .unwrap()
+                .unwrap()
+                .rating
+                < Rating::default()
+        );
+
+        // once the game is over, the player who stayed goes back to the lobby
+        app.update();
+        assert!(app.world().get_entity(right).is_none());
+        assert!(app.world().get::<InGame>(left).is_none());
+    }
+}
@@ -0,0 +1,358 @@
+use prometheus::{
+    Gauge, Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use warp::{http::header::CONTENT_TYPE, Filter, Rejection, Reply};
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    log::error,
+    prelude::{
+        App, EventReader, IntoSystemConfigs, Local, Plugin, PostUpdate, Query, Res, Resource, With,
+        Without,
+    },
+};
+use bevy_replicon::prelude::{ConnectedClients, ServerSet, ToClients};
+
+use game::{
+    ActionProcessed, ActionRejected, DictionaryLookup, Game, GameResult, InGame, Strike,
+    StrikeEvent,
+};
+
+use crate::{MatchQueue, QueueEntry};
+
+// seconds spent in the queue before being matched, up to the longest a bot lets anyone wait
+const QUEUE_WAIT_BUCKETS: [f64; 9] = [1., 2., 5., 10., 20., 30., 60., 120., 300.];
+// seconds taken by a single dictionary lookup, from a few microseconds up to building an infix index
+const DICTIONARY_LOOKUP_BUCKETS: [f64; 9] = [
+    0.000_001, 0.000_005, 0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.01, 0.1,
+];
+
+// Everything `/metrics` reports. The metrics are shared with the HTTP server, so cloning
+// this is cheap and every clone updates the same values.
+#[derive(Clone)]
+#[derive(Resource)]
+pub struct ServerMetrics {
+    registry: Registry,
+    connected_clients: IntGauge,
+    games_in_progress: IntGauge,
+    queue_length: IntGauge,
+    queue_wait: Histogram,
+    actions: IntCounterVec,
+    strikes: IntCounterVec,
+    dictionary_lookup_time: Histogram,
+    frame_time: Gauge,
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        let connected_clients =
+            IntGauge::new("wordfight_connected_clients", "Clients connected").unwrap();
+        let games_in_progress =
+            IntGauge::new("wordfight_games_in_progress", "Games not yet over").unwrap();
+        let queue_length = IntGauge::new(
+            "wordfight_queue_length",
+            "Players waiting in the matchmaking queue",
+        )
+        .unwrap();
+        let queue_wait = Histogram::with_opts(
+            HistogramOpts::new(
+                "wordfight_queue_wait_seconds",
+                "How long matched players waited in the queue",
+            )
+            .buckets(QUEUE_WAIT_BUCKETS.to_vec()),
+        )
+        .unwrap();
+        let actions = IntCounterVec::new(
+            Opts::new(
+                "wordfight_actions_total",
+                "Actions by whether they were accepted",
+            ),
+            &["outcome"],
+        )
+        .unwrap();
+        let strikes = IntCounterVec::new(
+            Opts::new("wordfight_strikes_total", "Strikes by outcome"),
+            &["outcome"],
+        )
+        .unwrap();
+        let dictionary_lookup_time = Histogram::with_opts(
+            HistogramOpts::new(
+                "wordfight_dictionary_lookup_seconds",
+                "Time taken to check an action against its dictionary",
+            )
+            .buckets(DICTIONARY_LOOKUP_BUCKETS.to_vec()),
+        )
+        .unwrap();
+        let frame_time = Gauge::new(
+            "wordfight_frame_time_seconds",
+            "Smoothed time taken by each frame of the server loop",
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(connected_clients.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(games_in_progress.clone()))
+            .unwrap();
+        registry.register(Box::new(queue_length.clone())).unwrap();
+        registry.register(Box::new(queue_wait.clone())).unwrap();
+        registry.register(Box::new(actions.clone())).unwrap();
+        registry.register(Box::new(strikes.clone())).unwrap();
+        registry
+            .register(Box::new(dictionary_lookup_time.clone()))
+            .unwrap();
+        registry.register(Box::new(frame_time.clone())).unwrap();
+
+        Self {
+            registry,
+            connected_clients,
+            games_in_progress,
+            queue_length,
+            queue_wait,
+            actions,
+            strikes,
+            dictionary_lookup_time,
+            frame_time,
+        }
+    }
+}
+
+impl ServerMetrics {
+    // The metrics in Prometheus' text format.
+    pub fn encode(&self) -> String {
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_else(|error| {
+                error!("Failed to encode metrics: {error}");
+                String::new()
+            })
+    }
+}
+
+// Keeps `ServerMetrics` up to date. Counters only ever go up, so rates
+// (such as actions per second) are left to whoever scrapes them.
+pub(crate) struct ServerMetricsPlugin {
+    pub metrics: ServerMetrics,
+}
+
+impl Plugin for ServerMetricsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.insert_resource(self.metrics.clone());
+        app.add_systems(
+            PostUpdate,
+            (
+                // replicon drains `ToClients` events when it sends them
+                Self::count_actions.before(ServerSet::Send),
+                Self::count_strikes,
+                Self::record_queue_waits,
+                Self::record_dictionary_lookups,
+                Self::update_gauges,
+            ),
+        );
+    }
+}
+
+impl ServerMetricsPlugin {
+    fn count_actions(
+        metrics: Res<ServerMetrics>,
+        mut processed: EventReader<ActionProcessed>,
+        mut rejected: EventReader<ToClients<ActionRejected>>,
+    ) {
+        for action in processed.read() {
+            let outcome = if action.outcome.is_accepted() {
+                "accepted"
+            } else {
+                "rejected"
+            };
+            metrics.actions.with_label_values(&[outcome]).inc();
+        }
+        // actions turned down before the game ever looked at them
+        let rejected = rejected.read().count() as u64;
+        metrics
+            .actions
+            .with_label_values(&["rejected"])
+            .inc_by(rejected);
+    }
+
+    fn count_strikes(metrics: Res<ServerMetrics>, mut strikes: EventReader<StrikeEvent>) {
+        for StrikeEvent { strike, .. } in strikes.read() {
+            let outcome = match strike {
+                Strike::Score(..) => "score",
+                Strike::Parry => "parry",
+                Strike::OverRange => "over_range",
+            };
+            metrics.strikes.with_label_values(&[outcome]).inc();
+        }
+    }
+
+    fn record_dictionary_lookups(
+        metrics: Res<ServerMetrics>,
+        mut lookups: EventReader<DictionaryLookup>,
+    ) {
+        for lookup in lookups.read() {
+            metrics
+                .dictionary_lookup_time
+                .observe(lookup.duration.as_secs_f64());
+        }
+    }
+
+    // Players who left the queue since last frame and are now in a game were matched,
+    // after waiting as long as the queue last said they had.
+    fn record_queue_waits(
+        metrics: Res<ServerMetrics>,
+        queue: Res<MatchQueue>,
+        in_game: Query<(), With<InGame>>,
+        mut waiting: Local<Vec<QueueEntry>>,
+    ) {
+        for entry in waiting.iter() {
+            if !queue.contains(entry.player) && in_game.contains(entry.player) {
+                metrics.queue_wait.observe(entry.waited.as_secs_f64());
+            }
+        }
+        *waiting = queue.entries().to_vec();
+    }
+
+    fn update_gauges(
+        metrics: Res<ServerMetrics>,
+        clients: Res<ConnectedClients>,
+        games: Query<(), (With<Game>, Without<GameResult>)>,
+        queue: Res<MatchQueue>,
+        diagnostics: Res<DiagnosticsStore>,
+    ) {
+        metrics.connected_clients.set(clients.len() as i64);
+        metrics.games_in_progress.set(games.iter().count() as i64);
+        metrics.queue_length.set(queue.len() as i64);
+        // diagnostics are measured in milliseconds
+        if let Some(frame_time) = diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+            .and_then(|diagnostic| diagnostic.smoothed())
+        {
+            metrics.frame_time.set(frame_time / 1000.);
+        }
+    }
+}
+
+// `/metrics`, for Prometheus to scrape.
+pub(crate) fn routes(
+    metrics: ServerMetrics,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(move || {
+            warp::reply::with_header(metrics.encode(), CONTENT_TYPE, prometheus::TEXT_FORMAT)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::{Action, ActionOutcome, PlayerBundle, PlayerSide, RejectionReason, Word};
+    use std::time::Duration;
+
+    use bevy::{
+        ecs::event::Events,
+        prelude::{Entity, ResMut},
+        MinimalPlugins,
+    };
+    use bevy_replicon::prelude::{ClientId, SendMode};
+
+    #[test]
+    fn test_metrics() {
+        let metrics = ServerMetrics::default();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<ActionProcessed>()
+            .add_event::<ToClients<ActionRejected>>()
+            .add_event::<StrikeEvent>()
+            .add_event::<DictionaryLookup>()
+            .init_resource::<DiagnosticsStore>()
+            .init_resource::<ConnectedClients>()
+            .insert_resource(MatchQueue::default())
+            .add_plugins(ServerMetricsPlugin {
+                metrics: metrics.clone(),
+            })
+            // stands in for replicon sending (and so draining) events to clients
+            .add_systems(
+                PostUpdate,
+                (|mut events: ResMut<Events<ToClients<ActionRejected>>>| {
+                    events.clear();
+                })
+                .in_set(ServerSet::Send),
+            );
+
+        let player = app.world_mut().spawn_empty().id();
+        let game = app.world_mut().spawn(Game).id();
+        app.world_mut()
+            .resource_mut::<MatchQueue>()
+            .join(player, Default::default());
+        app.world_mut()
+            .resource_mut::<MatchQueue>()
+            .tick(Duration::from_secs(3));
+        app.update();
+        assert!(metrics.encode().contains("wordfight_queue_length 1"));
+        assert!(metrics.encode().contains("wordfight_games_in_progress 1"));
+
+        app.world_mut().resource_mut::<MatchQueue>().leave(player);
+        app.world_mut()
+            .entity_mut(player)
+            .insert(PlayerBundle::new(PlayerSide::Left, game));
+        for outcome in [ActionOutcome::Accepted, ActionOutcome::NotAPrefix] {
+            app.world_mut().send_event(ActionProcessed {
+                game,
+                player,
+                side: PlayerSide::Left,
+                action: Action::Delete,
+                outcome,
+                word: Word::default(),
+            });
+        }
+        app.world_mut().send_event(StrikeEvent {
+            game,
+            strike: Strike::Parry,
+        });
+        app.world_mut().send_event(StrikeEvent {
+            game: Entity::PLACEHOLDER,
+            strike: Strike::OverRange,
+        });
+        app.world_mut().send_event(ToClients {
+            mode: SendMode::Direct(ClientId::new(1)),
+            event: ActionRejected {
+                action: Action::Delete,
+                reason: RejectionReason::NotInGame,
+            },
+        });
+        app.world_mut().send_event(DictionaryLookup {
+            duration: Duration::from_micros(20),
+        });
+        app.update();
+
+        let encoded = metrics.encode();
+        assert!(encoded.contains("wordfight_queue_length 0"));
+        assert!(encoded.contains("wordfight_queue_wait_seconds_bucket{le=\"5\"} 1"));
+        assert!(encoded.contains("wordfight_queue_wait_seconds_bucket{le=\"2\"} 0"));
+        assert!(encoded.contains("wordfight_actions_total{outcome=\"accepted\"} 1"));
+        assert!(encoded.contains("wordfight_actions_total{outcome=\"rejected\"} 2"));
+        assert!(encoded.contains("wordfight_strikes_total{outcome=\"parry\"} 1"));
+        assert!(encoded.contains("wordfight_strikes_total{outcome=\"over_range\"} 1"));
+        assert!(encoded.contains("wordfight_dictionary_lookup_seconds_bucket{le=\"0.00001\"} 0"));
+        assert!(encoded.contains("wordfight_dictionary_lookup_seconds_bucket{le=\"0.00005\"} 1"));
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let response = runtime.block_on(
+            warp::test::request()
+                .method("GET")
+                .path("/metrics")
+                .reply(&routes(metrics.clone())),
+        );
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        assert_eq!(String::from_utf8_lossy(response.body()), metrics.encode());
+    }
+}
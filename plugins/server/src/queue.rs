@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use bevy::prelude::{Entity, Resource};
+
+use game::Rating;
+
+// How picky the queue is about ratings. Every player starts out only accepting opponents
+// within `initial_gap` of their own rating, and accepts a wider gap the longer they wait.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct QueueSettings {
+    pub initial_gap: f64,
+    // how much the acceptable gap grows for every second spent waiting
+    pub gap_growth: f64,
+    pub max_gap: f64,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        Self {
+            initial_gap: 100.,
+            gap_growth: 20.,
+            max_gap: 800.,
+        }
+    }
+}
+
+impl QueueSettings {
+    pub fn acceptable_gap(&self, waited: Duration) -> f64 {
+        (self.initial_gap + self.gap_growth * waited.as_secs_f64()).min(self.max_gap)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueueEntry {
+    pub player: Entity,
+    pub rating: Rating,
+    pub waited: Duration,
+}
+
+// Players waiting for a match, kept in the order they joined.
+// This only does the bookkeeping, so that matchmaking can be tested without a server.
+#[derive(Clone, Debug, Default)]
+#[derive(Resource)]
+pub struct MatchQueue {
+    settings: QueueSettings,
+    entries: Vec<QueueEntry>,
+}
+
+impl MatchQueue {
+    pub fn new(settings: QueueSettings) -> Self {
+        Self {
+            settings,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &QueueSettings {
+        &self.settings
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, player: Entity) -> bool {
+        self.entries.iter().any(|entry| entry.player == player)
+    }
+
+    pub fn entries(&self) -> &[QueueEntry] {
+        &self.entries
+    }
+
+    // Adds a player to the back of the queue. Players already waiting keep their place.
+    pub fn join(&mut self, player: Entity, rating: Rating) {
+        if !self.contains(player) {
+            self.entries.push(QueueEntry {
+                player,
+                rating,
+                waited: Duration::ZERO,
+            });
+        }
+    }
+
+    pub fn leave(&mut self, player: Entity) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.player != player);
+        self.entries.len() != len
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        self.entries.retain(|entry| keep(entry.player));
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        for entry in &mut self.entries {
+            entry.waited += delta;
+        }
+    }
+
+    // Removes and returns every pair of players that will accept each other. Whoever has waited
+    // longest is matched first, with the closest rated player in range. Two players are in range
+    // when their ratings are within the acceptable gap of whichever of them has waited longer,
+    // so that a long wait is not stretched out by newcomers.
+    pub fn take_matches(&mut self) -> Vec<[Entity; 2]> {
+        let mut matches = Vec::new();
+        let mut index = 0;
+        while index < self.entries.len() {
+            let entry = self.entries[index];
+            let gap = self.settings.acceptable_gap(entry.waited);
+            let opponent = self
+                .entries
+                .iter()
+                .enumerate()
+                .skip(index + 1)
+                .map(|(other, candidate)| (other, (*entry.rating - *candidate.rating).abs()))
+                .filter(|(_, difference)| *difference <= gap)
+                .min_by(|(_, difference1), (_, difference2)| difference1.total_cmp(difference2));
+            match opponent {
+                Some((other, _)) => {
+                    let opponent = self.entries.remove(other);
+                    self.entries.remove(index);
+                    matches.push([entry.player, opponent.player]);
+                }
+                None => index += 1,
+            }
+        }
+        matches
+    }
+
+    // Removes and returns every player that has waited at least `wait` without a match.
+    pub fn take_waited(&mut self, wait: Duration) -> Vec<Entity> {
+        let (waited, waiting) = self
+            .entries
+            .drain(..)
+            .partition::<Vec<_>, _>(|entry| entry.waited >= wait);
+        self.entries = waiting;
+        waited.into_iter().map(|entry| entry.player).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(index: u32) -> Entity {
+        Entity::from_raw(index)
+    }
+
+    #[test]
+    fn test_acceptable_gap() {
+        let settings = QueueSettings::default();
+        assert_eq!(settings.acceptable_gap(Duration::ZERO), 100.);
+        assert_eq!(settings.acceptable_gap(Duration::from_secs(5)), 200.);
+        assert_eq!(settings.acceptable_gap(Duration::from_secs(600)), 800.);
+    }
+
+    #[test]
+    fn test_matches_closest_ratings() {
+        let mut queue = MatchQueue::default();
+        queue.join(player(0), Rating::new(1500.));
+        queue.join(player(1), Rating::new(1300.));
+        queue.join(player(2), Rating::new(1450.));
+        queue.join(player(3), Rating::new(1320.));
+        // joining again does not lose your place
+        queue.join(player(0), Rating::new(1500.));
+        assert_eq!(queue.len(), 4);
+
+        assert_eq!(
+            queue.take_matches(),
+            vec![[player(0), player(2)], [player(1), player(3)]]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_gap_widens_with_wait() {
+        let mut queue = MatchQueue::default();
+        queue.join(player(0), Rating::new(1500.));
+        queue.join(player(1), Rating::new(1800.));
+        assert!(queue.take_matches().is_empty());
+
+        queue.tick(Duration::from_secs(5));
+        assert!(queue.take_matches().is_empty());
+        assert_eq!(queue.entries()[0].waited, Duration::from_secs(5));
+
+        queue.tick(Duration::from_secs(5));
+        assert_eq!(queue.take_matches(), vec![[player(0), player(1)]]);
+
+        // the longest wait decides the gap, so newcomers can match with long-waiting players
+        queue.join(player(2), Rating::new(1500.));
+        queue.tick(Duration::from_secs(10));
+        queue.join(player(3), Rating::new(1780.));
+        assert_eq!(queue.take_matches(), vec![[player(2), player(3)]]);
+    }
+
+    #[test]
+    fn test_leave_and_take_waited() {
+        let mut queue = MatchQueue::default();
+        queue.join(player(0), Rating::new(1000.));
+        queue.tick(Duration::from_secs(10));
+        queue.join(player(1), Rating::new(2000.));
+        queue.join(player(2), Rating::new(3000.));
+
+        assert!(queue.leave(player(2)));
+        assert!(!queue.leave(player(2)));
+        assert_eq!(queue.take_waited(Duration::from_secs(10)), vec![player(0)]);
+        assert_eq!(queue.take_waited(Duration::from_secs(10)), vec![]);
+        assert!(queue.contains(player(1)));
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy::prelude::{Deref, DerefMut, Resource, Timer, TimerMode};
+
+// Set once the server has been asked to stop (e.g. by SIGTERM). Shared with the token server,
+// which stops handing out tokens from then on.
+#[derive(Clone, Debug, Default)]
+#[derive(Resource)]
+pub struct ShutdownSignal(Arc<AtomicBool>);
+
+impl ShutdownSignal {
+    // Raises the signal on SIGTERM or SIGINT (and SIGQUIT on unix). If the server is asked
+    // again while it is still draining, it exits straight away.
+    pub fn listen() -> std::io::Result<Self> {
+        let signal = Self::default();
+        for kind in signal_hook::consts::TERM_SIGNALS {
+            signal_hook::flag::register_conditional_shutdown(*kind, 1, signal.0.clone())?;
+            signal_hook::flag::register(*kind, signal.0.clone())?;
+        }
+        Ok(signal)
+    }
+
+    pub fn raise(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_raised(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// How long games already being played are given to finish once the server starts shutting down.
+#[derive(Clone, Copy, Debug)]
+#[derive(Deref, Resource)]
+pub struct DrainTimeout(pub Duration);
+
+// Present while the server is shutting down. Until `deadline` finishes, games in progress are
+// played out, but no new games start and no new clients are let in.
+#[derive(Clone, Debug)]
+#[derive(Deref, DerefMut, Resource)]
+pub struct Draining {
+    deadline: Timer,
+}
+
+impl Draining {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            deadline: Timer::new(timeout, TimerMode::Once),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ServerPlugin;
+    use game::{Client, Game, GameEnded, GamePlayers, GameResult, Rating, Score, ServerShutdown};
+
+    use bevy::{
+        ecs::event::Events,
+        prelude::{resource_exists, App, AppExit, IntoSystemConfigs, Update},
+        MinimalPlugins,
+    };
+    use bevy_replicon::prelude::{ClientId, ToClients};
+
+    #[test]
+    fn test_drain() {
+        let signal = ShutdownSignal::default();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<ToClients<ServerShutdown>>()
+            .add_event::<GameEnded>()
+            .insert_resource(signal.clone())
+            .insert_resource(DrainTimeout(Duration::ZERO))
+            .add_systems(
+                Update,
+                (
+                    ServerPlugin::start_draining,
+                    ServerPlugin::drain.run_if(resource_exists::<Draining>),
+                    ServerPlugin::handle_game_ended,
+                )
+                    .chain(),
+            );
+        let mut player = |client, rating| {
+            let client = Client::from(ClientId::new(client));
+            app.world_mut()
+                .spawn((client, rating, Score::default()))
+                .id()
+        };
+        let left = player(1, Rating::new(1600.));
+        let right = player(2, Rating::default());
+        let game = app
+            .world_mut()
+            .spawn((Game, GamePlayers { left, right }))
+            .id();
+
+        app.update();
+        assert!(!app.world().contains_resource::<Draining>());
+
+        signal.raise();
+        app.update();
+        assert!(app.world().contains_resource::<Draining>());
+        let shutdowns = app.world().resource::<Events<ToClients<ServerShutdown>>>();
+        assert_eq!(
+            shutdowns
+                .get_reader()
+                .read(shutdowns)
+                .map(|shutdown| shutdown.event)
+                .collect::<Vec<_>>(),
+            vec![ServerShutdown { deadline_secs: 0 }]
+        );
+
+        // the deadline has passed, so the game is called off without rating it
+        app.update();
+        let result = GameResult::aborted(0, 0);
+        assert_eq!(app.world().get::<GameResult>(game), Some(&result));
+        let ended = app.world().resource::<Events<GameEnded>>();
+        assert_eq!(ended.get_reader().read(ended).count(), 1);
+        assert_eq!(app.world().get::<Rating>(left), Some(&Rating::new(1600.)));
+        assert_eq!(app.world().get::<Rating>(right), Some(&Rating::default()));
+        assert!(app.world().resource::<Events<AppExit>>().is_empty());
+
+        // as is any game started after the deadline
+        app.world_mut().despawn(game);
+        let late = app
+            .world_mut()
+            .spawn((Game, GamePlayers { left, right }))
+            .id();
+        app.update();
+        assert_eq!(app.world().get::<GameResult>(late), Some(&result));
+
+        // and once they are gone, the server exits
+        app.world_mut().despawn(late);
+        app.update();
+        let exits = app.world().resource::<Events<AppExit>>();
+        assert_eq!(
+            exits.get_reader().read(exits).collect::<Vec<_>>(),
+            vec![&AppExit::Success]
+        );
+    }
+}
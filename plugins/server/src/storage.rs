@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+
+use bevy::prelude::Resource;
+
+use game::{GameResult, GameSettings, PlayerId, Rating};
+
+mod memory;
+pub use memory::*;
+mod sqlite;
+pub use sqlite::*;
+
+pub type MatchId = u64;
+
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+pub struct PlayerProfile {
+    pub id: PlayerId,
+    pub name: String,
+    pub rating: Rating,
+    // seconds since the unix epoch
+    pub created_at: u64,
+}
+
+impl PlayerProfile {
+    // what players are called until they pick a name of their own
+    pub fn default_name(id: PlayerId) -> String {
+        format!("Player {id}")
+    }
+}
+
+// A finished game. Bots (and anyone playing without an account) have no player id.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+pub struct MatchRecord {
+    pub left: Option<PlayerId>,
+    pub right: Option<PlayerId>,
+    pub arena_size: usize,
+    #[serde(flatten)]
+    pub settings: GameSettings,
+    pub result: GameResult,
+    #[serde(rename = "duration_ms", with = "duration_millis")]
+    pub duration: Duration,
+    // seconds since the unix epoch
+    pub finished_at: u64,
+}
+
+// Where player profiles and match history are kept. Accounts are looked up by a hash of their
+// key, so the keys themselves are never stored.
+pub trait Storage: Send + 'static {
+    // Makes a new player with the default name and rating.
+    fn create_player(
+        &mut self,
+        key_hash: &[u8],
+        created_at: u64,
+    ) -> Result<PlayerProfile, StorageError>;
+
+    fn player(&self, id: PlayerId) -> Result<Option<PlayerProfile>, StorageError>;
+
+    fn player_by_key(&self, key_hash: &[u8]) -> Result<Option<PlayerProfile>, StorageError>;
+
+    fn set_rating(&mut self, id: PlayerId, rating: Rating) -> Result<(), StorageError>;
+
+    // Banned players are refused connect tokens.
+    fn set_banned(&mut self, id: PlayerId, banned: bool) -> Result<(), StorageError>;
+
+    fn is_banned(&self, id: PlayerId) -> Result<bool, StorageError>;
+
+    fn record_match(&mut self, record: &MatchRecord) -> Result<MatchId, StorageError>;
+
+    fn match_record(&self, id: MatchId) -> Result<Option<MatchRecord>, StorageError>;
+
+    // the most recent `limit` matches `player` played in, newest first
+    fn player_matches(
+        &self,
+        player: PlayerId,
+        limit: usize,
+    ) -> Result<Vec<(MatchId, MatchRecord)>, StorageError>;
+
+    // the `limit` best rated players, best first
+    fn top_players(&self, limit: usize) -> Result<Vec<PlayerProfile>, StorageError>;
+}
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Failed to encode or decode a stored value: {0}")]
+    Encoding(#[from] serde_json::Error),
+    #[error("Player {0} does not exist")]
+    UnknownPlayer(PlayerId),
+}
+
+// The server's storage, shared so that it can also be read outside of Bevy's systems.
+#[derive(Clone)]
+#[derive(Resource)]
+pub struct Persistence(Arc<Mutex<dyn Storage>>);
+
+impl Persistence {
+    pub fn new(storage: impl Storage) -> Self {
+        Persistence(Arc::new(Mutex::new(storage)))
+    }
+
+    pub fn storage(&self) -> MutexGuard<'_, dyn Storage> {
+        // storage calls do not panic halfway through a write, so a poisoned lock is still usable
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+mod duration_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+pub fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::{PlayerSide, WinCondition};
+
+    fn record(left: Option<PlayerId>, right: Option<PlayerId>) -> MatchRecord {
+        MatchRecord {
+            left,
+            right,
+            arena_size: 7,
+            settings: GameSettings::default().with_win_condition(WinCondition::Points(3)),
+            result: GameResult {
+                winner: Some(PlayerSide::Left),
+                left_score: 3,
+                right_score: 1,
+                aborted: false,
+                forfeit: false,
+            },
+            duration: Duration::from_millis(61_250),
+            finished_at: 1_700_000_000,
+        }
+    }
+
+    fn check_storage(storage: &mut dyn Storage) {
+        let alice = storage.create_player(b"alice", 100).unwrap();
+        let bob = storage.create_player(b"bob", 200).unwrap();
+        assert_ne!(alice.id, bob.id);
+        assert_eq!(alice.name, PlayerProfile::default_name(alice.id));
+        assert_eq!(alice.rating, Rating::default());
+        assert_eq!(storage.player(bob.id).unwrap(), Some(bob.clone()));
+        assert_eq!(
+            storage.player_by_key(b"alice").unwrap(),
+            Some(alice.clone())
+        );
+        assert_eq!(storage.player_by_key(b"carol").unwrap(), None);
+
+        storage.set_rating(bob.id, Rating::new(1600.)).unwrap();
+        assert_eq!(
+            storage.player(bob.id).unwrap().unwrap().rating,
+            Rating::new(1600.)
+        );
+        assert!(matches!(
+            storage.set_rating(PlayerId::new(99), Rating::default()),
+            Err(StorageError::UnknownPlayer(_))
+        ));
+        let top = storage.top_players(10).unwrap();
+        assert_eq!(
+            top.iter().map(|player| player.id).collect::<Vec<_>>(),
+            vec![bob.id, alice.id]
+        );
+        assert_eq!(storage.top_players(1).unwrap().len(), 1);
+
+        assert!(!storage.is_banned(alice.id).unwrap());
+        storage.set_banned(alice.id, true).unwrap();
+        assert!(storage.is_banned(alice.id).unwrap());
+        assert!(!storage.is_banned(bob.id).unwrap());
+        storage.set_banned(alice.id, false).unwrap();
+        assert!(!storage.is_banned(alice.id).unwrap());
+        assert!(matches!(
+            storage.set_banned(PlayerId::new(99), true),
+            Err(StorageError::UnknownPlayer(_))
+        ));
+
+        let first = storage
+            .record_match(&record(Some(alice.id), Some(bob.id)))
+            .unwrap();
+        let second = storage.record_match(&record(Some(bob.id), None)).unwrap();
+        let aborted = MatchRecord {
+            result: GameResult::aborted(1, 1),
+            ..record(Some(alice.id), None)
+        };
+        let third = storage.record_match(&aborted).unwrap();
+        assert_eq!(storage.match_record(third).unwrap(), Some(aborted));
+        let forfeit = MatchRecord {
+            result: GameResult::forfeit(PlayerSide::Left, 2, 0),
+            ..record(Some(alice.id), None)
+        };
+        let fourth = storage.record_match(&forfeit).unwrap();
+        assert_eq!(storage.match_record(fourth).unwrap(), Some(forfeit));
+        assert_eq!(
+            storage.match_record(first).unwrap(),
+            Some(record(Some(alice.id), Some(bob.id)))
+        );
+        assert_eq!(storage.match_record(fourth + 1).unwrap(), None);
+        let history = storage.player_matches(bob.id, 10).unwrap();
+        assert_eq!(
+            history.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![second, first]
+        );
+        assert_eq!(storage.player_matches(alice.id, 10).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_storage(&mut MemoryStorage::default());
+    }
+
+    #[test]
+    fn test_sqlite_storage() {
+        check_storage(&mut SqliteStorage::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_reader() {
+        let path = std::env::temp_dir().join(format!("wordfight-{}.db", std::process::id()));
+        let mut storage = SqliteStorage::open(&path).unwrap();
+        let mut reader = SqliteStorage::open_read_only(&path).unwrap();
+        let player = storage.create_player(b"key", 0).unwrap();
+        assert_eq!(reader.player(player.id).unwrap(), Some(player));
+        assert!(reader.create_player(b"other", 0).is_err());
+        drop((storage, reader));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use game::{PlayerId, Rating};
+
+use crate::{MatchId, MatchRecord, PlayerProfile, Storage, StorageError};
+
+// Keeps everything in memory, so nothing survives a restart. Used when no database is configured.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    // indexed by id - 1
+    players: Vec<(PlayerProfile, Vec<u8>)>,
+    // indexed by id - 1
+    matches: Vec<MatchRecord>,
+    banned: HashSet<PlayerId>,
+}
+
+impl MemoryStorage {
+    fn player_mut(&mut self, id: PlayerId) -> Option<&mut PlayerProfile> {
+        let index = (*id as usize).checked_sub(1)?;
+        self.players.get_mut(index).map(|(player, _)| player)
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn create_player(
+        &mut self,
+        key_hash: &[u8],
+        created_at: u64,
+    ) -> Result<PlayerProfile, StorageError> {
+        let id = PlayerId::new(self.players.len() as u64 + 1);
+        let player = PlayerProfile {
+            id,
+            name: PlayerProfile::default_name(id),
+            rating: Rating::default(),
+            created_at,
+        };
+        self.players.push((player.clone(), key_hash.to_vec()));
+        Ok(player)
+    }
+
+    fn player(&self, id: PlayerId) -> Result<Option<PlayerProfile>, StorageError> {
+        let Some(index) = (*id as usize).checked_sub(1) else {
+            return Ok(None);
+        };
+        Ok(self.players.get(index).map(|(player, _)| player.clone()))
+    }
+
+    fn player_by_key(&self, key_hash: &[u8]) -> Result<Option<PlayerProfile>, StorageError> {
+        Ok(self
+            .players
+            .iter()
+            .find(|(_, hash)| hash == key_hash)
+            .map(|(player, _)| player.clone()))
+    }
+
+    fn set_rating(&mut self, id: PlayerId, rating: Rating) -> Result<(), StorageError> {
+        let player = self.player_mut(id).ok_or(StorageError::UnknownPlayer(id))?;
+        player.rating = rating;
+        Ok(())
+    }
+
+    fn set_banned(&mut self, id: PlayerId, banned: bool) -> Result<(), StorageError> {
+        self.player_mut(id).ok_or(StorageError::UnknownPlayer(id))?;
+        if banned {
+            self.banned.insert(id);
+        } else {
+            self.banned.remove(&id);
+        }
+        Ok(())
+    }
+
+    fn is_banned(&self, id: PlayerId) -> Result<bool, StorageError> {
+        Ok(self.banned.contains(&id))
+    }
+
+    fn record_match(&mut self, record: &MatchRecord) -> Result<MatchId, StorageError> {
+        self.matches.push(record.clone());
+        Ok(self.matches.len() as MatchId)
+    }
+
+    fn match_record(&self, id: MatchId) -> Result<Option<MatchRecord>, StorageError> {
+        let Some(index) = (id as usize).checked_sub(1) else {
+            return Ok(None);
+        };
+        Ok(self.matches.get(index).cloned())
+    }
+
+    fn player_matches(
+        &self,
+        player: PlayerId,
+        limit: usize,
+    ) -> Result<Vec<(MatchId, MatchRecord)>, StorageError> {
+        Ok(self
+            .matches
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, record)| record.left == Some(player) || record.right == Some(player))
+            .take(limit)
+            .map(|(index, record)| (index as MatchId + 1, record.clone()))
+            .collect())
+    }
+
+    fn top_players(&self, limit: usize) -> Result<Vec<PlayerProfile>, StorageError> {
+        let mut players = self
+            .players
+            .iter()
+            .map(|(player, _)| player.clone())
+            .collect::<Vec<_>>();
+        players.sort_by(|player1, player2| player2.rating.total_cmp(&player1.rating));
+        players.truncate(limit);
+        Ok(players)
+    }
+}
@@ -0,0 +1,275 @@
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use std::{path::Path, time::Duration};
+
+use game::{GameResult, GameSettings, PlayerId, PlayerSide, Rating};
+
+use crate::{MatchId, MatchRecord, PlayerProfile, Storage, StorageError};
+
+// Each entry brings a database from the previous version (kept in `PRAGMA user_version`) up to
+// date. Databases made before versions were tracked already have the first one's tables.
+const MIGRATIONS: &[&str] = &[
+    "
+CREATE TABLE IF NOT EXISTS players (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    key_hash BLOB NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    rating REAL NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS players_rating ON players (rating);
+CREATE TABLE IF NOT EXISTS matches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    left_player INTEGER REFERENCES players (id),
+    right_player INTEGER REFERENCES players (id),
+    arena_size INTEGER NOT NULL,
+    rules TEXT NOT NULL,
+    dictionary TEXT NOT NULL,
+    winner TEXT,
+    left_score INTEGER NOT NULL,
+    right_score INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    finished_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS matches_left_player ON matches (left_player);
+CREATE INDEX IF NOT EXISTS matches_right_player ON matches (right_player);
+",
+    "ALTER TABLE players ADD COLUMN banned INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE matches ADD COLUMN aborted INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE matches ADD COLUMN forfeit INTEGER NOT NULL DEFAULT 0;",
+];
+
+// how long a connection waits for another one to finish writing before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const PLAYER_COLUMNS: &str = "id, name, rating, created_at";
+const MATCH_COLUMNS: &str = "id, left_player, right_player, arena_size, rules, dictionary, \
+    winner, left_score, right_score, duration_ms, finished_at, aborted, forfeit";
+
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    // Opens (or creates) the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let connection = Connection::open(path)?;
+        // in WAL mode, readers on other connections do not wait for the server's writes
+        connection.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        Self::new(connection)
+    }
+
+    // Opens another connection to a database `open` has already set up, which can only read.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let connection = Connection::open_with_flags(path, flags)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(SqliteStorage { connection })
+    }
+
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(mut connection: Connection) -> Result<Self, StorageError> {
+        let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", index + 1)?;
+            transaction.commit()?;
+        }
+        Ok(SqliteStorage { connection })
+    }
+
+    fn player_from_row(row: &Row) -> rusqlite::Result<PlayerProfile> {
+        Ok(PlayerProfile {
+            id: PlayerId::new(row.get(0)?),
+            name: row.get(1)?,
+            rating: Rating::new(row.get(2)?),
+            created_at: row.get(3)?,
+        })
+    }
+
+    fn match_from_row(row: &Row) -> Result<(MatchId, MatchRecord), StorageError> {
+        // the dictionary has a column of its own, and the rest of the settings are kept
+        // together as JSON so that new rules do not need a schema change
+        let mut settings: serde_json::Value = serde_json::from_str(&row.get::<_, String>(4)?)?;
+        settings["dictionary"] = row.get::<_, String>(5)?.into();
+        let settings: GameSettings = serde_json::from_value(settings)?;
+        let winner = match row.get::<_, Option<String>>(6)?.as_deref() {
+            Some("left") => Some(PlayerSide::Left),
+            Some("right") => Some(PlayerSide::Right),
+            _ => None,
+        };
+        let record = MatchRecord {
+            left: row.get::<_, Option<u64>>(1)?.map(PlayerId::new),
+            right: row.get::<_, Option<u64>>(2)?.map(PlayerId::new),
+            arena_size: row.get(3)?,
+            settings,
+            result: GameResult {
+                winner,
+                left_score: row.get(7)?,
+                right_score: row.get(8)?,
+                aborted: row.get(11)?,
+                forfeit: row.get(12)?,
+            },
+            duration: Duration::from_millis(row.get(9)?),
+            finished_at: row.get(10)?,
+        };
+        Ok((row.get(0)?, record))
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn create_player(
+        &mut self,
+        key_hash: &[u8],
+        created_at: u64,
+    ) -> Result<PlayerProfile, StorageError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO players (key_hash, name, rating, created_at) VALUES (?1, '', ?2, ?3)",
+            params![key_hash, *Rating::default(), created_at],
+        )?;
+        let id = PlayerId::new(transaction.last_insert_rowid() as u64);
+        let name = PlayerProfile::default_name(id);
+        transaction.execute(
+            "UPDATE players SET name = ?1 WHERE id = ?2",
+            params![name, *id],
+        )?;
+        transaction.commit()?;
+        Ok(PlayerProfile {
+            id,
+            name,
+            rating: Rating::default(),
+            created_at,
+        })
+    }
+
+    fn player(&self, id: PlayerId) -> Result<Option<PlayerProfile>, StorageError> {
+        Ok(self
+            .connection
+            .query_row(
+                &format!("SELECT {PLAYER_COLUMNS} FROM players WHERE id = ?1"),
+                params![*id],
+                Self::player_from_row,
+            )
+            .optional()?)
+    }
+
+    fn player_by_key(&self, key_hash: &[u8]) -> Result<Option<PlayerProfile>, StorageError> {
+        Ok(self
+            .connection
+            .query_row(
+                &format!("SELECT {PLAYER_COLUMNS} FROM players WHERE key_hash = ?1"),
+                params![key_hash],
+                Self::player_from_row,
+            )
+            .optional()?)
+    }
+
+    fn set_rating(&mut self, id: PlayerId, rating: Rating) -> Result<(), StorageError> {
+        let updated = self.connection.execute(
+            "UPDATE players SET rating = ?1 WHERE id = ?2",
+            params![*rating, *id],
+        )?;
+        if updated == 0 {
+            return Err(StorageError::UnknownPlayer(id));
+        }
+        Ok(())
+    }
+
+    fn set_banned(&mut self, id: PlayerId, banned: bool) -> Result<(), StorageError> {
+        let updated = self.connection.execute(
+            "UPDATE players SET banned = ?1 WHERE id = ?2",
+            params![banned, *id],
+        )?;
+        if updated == 0 {
+            return Err(StorageError::UnknownPlayer(id));
+        }
+        Ok(())
+    }
+
+    fn is_banned(&self, id: PlayerId) -> Result<bool, StorageError> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT banned FROM players WHERE id = ?1",
+                params![*id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(false))
+    }
+
+    fn record_match(&mut self, record: &MatchRecord) -> Result<MatchId, StorageError> {
+        let mut rules = serde_json::to_value(&record.settings)?;
+        if let Some(rules) = rules.as_object_mut() {
+            rules.remove("dictionary");
+        }
+        let rules = rules.to_string();
+        let winner = record.result.winner.map(|side| match side {
+            PlayerSide::Left => "left",
+            PlayerSide::Right => "right",
+        });
+        self.connection.execute(
+            "INSERT INTO matches (left_player, right_player, arena_size, rules, dictionary, winner, \
+            left_score, right_score, duration_ms, finished_at, aborted, forfeit) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                record.left.map(|id| *id),
+                record.right.map(|id| *id),
+                record.arena_size,
+                rules,
+                record.settings.dictionary.as_str(),
+                winner,
+                record.result.left_score,
+                record.result.right_score,
+                record.duration.as_millis() as u64,
+                record.finished_at,
+                record.result.aborted,
+                record.result.forfeit,
+            ],
+        )?;
+        Ok(self.connection.last_insert_rowid() as MatchId)
+    }
+
+    fn match_record(&self, id: MatchId) -> Result<Option<MatchRecord>, StorageError> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {MATCH_COLUMNS} FROM matches WHERE id = ?1"
+        ))?;
+        let mut rows = statement.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::match_from_row(row)?.1)),
+            None => Ok(None),
+        }
+    }
+
+    fn player_matches(
+        &self,
+        player: PlayerId,
+        limit: usize,
+    ) -> Result<Vec<(MatchId, MatchRecord)>, StorageError> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {MATCH_COLUMNS} FROM matches WHERE left_player = ?1 OR right_player = ?1 \
+            ORDER BY id DESC LIMIT ?2"
+        ))?;
+        let mut rows = statement.query(params![*player, limit])?;
+        let mut matches = Vec::new();
+        while let Some(row) = rows.next()? {
+            matches.push(Self::match_from_row(row)?);
+        }
+        Ok(matches)
+    }
+
+    fn top_players(&self, limit: usize) -> Result<Vec<PlayerProfile>, StorageError> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {PLAYER_COLUMNS} FROM players ORDER BY rating DESC, id LIMIT ?1"
+        ))?;
+        let players = statement
+            .query_map(params![limit], Self::player_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(players)
+    }
+}
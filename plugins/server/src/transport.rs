@@ -1,116 +1,316 @@
-use std::net::SocketAddr;
-use warp::Filter;
+use futures_util::Stream;
+use sha2::{Digest, Sha256};
+use std::{
+    net::{IpAddr, SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex, PoisonError,
+    },
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+use warp::{http::StatusCode, Filter, Rejection, Reply};
 
-use bevy::prelude::{App, Plugin, Resource};
+use bevy::{
+    log::{error, info},
+    prelude::{App, Plugin, Resource},
+};
 
-use renet2::transport::WebServerDestination;
+use renet2::transport::{
+    ConnectToken, NetcodeServerTransport, ServerAuthentication, ServerSetupConfig,
+    TokenGenerationError, WebTransportServer, WebTransportServerConfig, NETCODE_KEY_BYTES,
+};
 
-use game::PROTOCOL_ID;
+use game::{ServerToken, PROTOCOL_ID};
+
+use crate::{
+    admin, api, decode_key, find_player, key_to_user_data, metrics, AccountKey, AdminRequest,
+    ConfigError, LiveGames, Persistence, ServerConfig, ServerMetrics, ShutdownSignal, StorageError,
+};
+
+// how long a client has to start connecting once it has its token
+const TOKEN_EXPIRE_SECS: u64 = 30;
+// how long a connection may go quiet before it is dropped
+const TOKEN_TIMEOUT_SECS: i32 = 15;
+
+// Everything the server listens on, bound when the `ServerPlugin` is made so that a port
+// that is already taken stops the server with a `ConfigError` before it starts.
+pub struct ServerSockets {
+    runtime: tokio::runtime::Runtime,
+    transport: NetcodeServerTransport,
+    tokens_listener: TcpListener,
+    metrics_listener: TcpListener,
+    // what connect tokens tell clients to connect to
+    public_addr: SocketAddr,
+    private_key: [u8; NETCODE_KEY_BYTES],
+    cert_hash: Vec<u8>,
+}
+
+impl ServerSockets {
+    pub fn bind(
+        config: &ServerConfig,
+        private_key: [u8; NETCODE_KEY_BYTES],
+    ) -> Result<Self, ConfigError> {
+        let server_addr = SocketAddr::new(config.bind_ip, config.port);
+        let public_addr = SocketAddr::new(config.public_ip(), config.port);
+        let tokens_listener = listen(SocketAddr::new(config.bind_ip, config.tokens_port))?;
+        let metrics_listener = listen(SocketAddr::new(config.bind_ip, config.metrics_port))?;
+
+        let (cert, key) = self_signed_certificate(
+            config.public_ip(),
+            time::Duration::days(config.cert_lifetime_days as i64),
+        )
+        .map_err(ConfigError::Certificate)?;
+        let cert_hash = Sha256::digest(&cert.0).to_vec();
+
+        println!("Opening WT Socket on {}", server_addr);
+        let runtime = tokio::runtime::Runtime::new().map_err(ConfigError::Runtime)?;
+        let socket = WebTransportServer::new(
+            WebTransportServerConfig {
+                cert,
+                key,
+                listen: server_addr,
+                max_clients: config.max_clients,
+            },
+            runtime.handle().clone(),
+        )
+        .map_err(|error| ConfigError::Bind {
+            address: server_addr,
+            reason: error.to_string(),
+        })?;
+
+        let current_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        let server_config = ServerSetupConfig {
+            current_time,
+            max_clients: config.max_clients,
+            protocol_id: PROTOCOL_ID,
+            socket_addresses: vec![vec![public_addr]],
+            authentication: ServerAuthentication::Secure { private_key },
+        };
+        let transport = NetcodeServerTransport::new(server_config, socket).map_err(|error| {
+            ConfigError::Bind {
+                address: server_addr,
+                reason: error.to_string(),
+            }
+        })?;
+
+        Ok(Self {
+            runtime,
+            transport,
+            tokens_listener,
+            metrics_listener,
+            public_addr,
+            private_key,
+            cert_hash,
+        })
+    }
+}
+
+fn listen(address: SocketAddr) -> Result<TcpListener, ConfigError> {
+    TcpListener::bind(address)
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .map_err(|error| ConfigError::Bind {
+            address,
+            reason: error.to_string(),
+        })
+}
+
+// WebTransport only lets browsers pin a certificate by its hash if it is an ECDSA P-256
+// certificate valid for at most two weeks.
+fn self_signed_certificate(
+    ip: IpAddr,
+    lifetime: time::Duration,
+) -> Result<(rustls::Certificate, rustls::PrivateKey), rcgen::Error> {
+    let mut params = rcgen::CertificateParams::new(vec![ip.to_string()]);
+    params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+    params.not_before = time::OffsetDateTime::now_utc();
+    params.not_after = params.not_before + lifetime;
+    let certificate = rcgen::Certificate::from_params(params)?;
+    Ok((
+        rustls::Certificate(certificate.serialize_der()?),
+        rustls::PrivateKey(certificate.serialize_private_key_der()),
+    ))
+}
 
 pub struct ServerTransportPlugin {
-    pub port: String,
-    pub wt_tokens_port: String,
+    // handed over to the app when the plugin is built
+    pub sockets: Mutex<Option<ServerSockets>>,
+    pub cors_origins: Vec<String>,
+    // what the HTTP API serves
+    pub persistence: Persistence,
+    pub live_games: LiveGames,
+    // the admin API is only served with a token
+    pub admin_token: Option<String>,
+    pub admin_requests: mpsc::Sender<AdminRequest>,
+    // served at `/metrics`, on a port of its own
+    pub metrics: ServerMetrics,
+    // no more tokens are handed out once this is raised
+    pub shutdown: ShutdownSignal,
 }
 
 impl Plugin for ServerTransportPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(NativeServerTransportPlugin::ip(
-            "0.0.0.0",
-            self.port.as_str(),
-            self.wt_tokens_port.as_str(),
+        #[derive(Resource)]
+        pub struct TokioRuntime(#[allow(dead_code)] tokio::runtime::Runtime);
+
+        let Some(ServerSockets {
+            runtime,
+            transport,
+            tokens_listener,
+            metrics_listener,
+            public_addr,
+            private_key,
+            cert_hash,
+        }) = self
+            .sockets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        else {
+            error!("The server's sockets were already handed to another app");
+            return;
+        };
+
+        // ids are only ever handed out here, so no two clients can share (or pick) one
+        let next_client_id = Arc::new(AtomicU64::new(1));
+
+        let cors = warp::cors()
+            .allow_method("GET")
+            // the token route reads account keys from the `Authorization` header
+            .allow_header("authorization")
+            .allow_origins(self.cors_origins.iter().map(String::as_str));
+        let api = api::routes(self.persistence.clone(), self.live_games.clone());
+        let admin = admin::routes(self.admin_token.clone(), self.admin_requests.clone());
+        let persistence = self.persistence.clone();
+        let shutdown = self.shutdown.clone();
+        runtime.spawn(async move {
+            let serve_tokens = warp::path("token")
+                .and(warp::path::end())
+                .and(warp::get())
+                .and(warp::header::optional::<String>("authorization"))
+                .map(move |authorization: Option<String>| {
+                    if shutdown.is_raised() {
+                        return warp::reply::with_status(
+                            String::new(),
+                            StatusCode::SERVICE_UNAVAILABLE,
+                        );
+                    }
+                    let client_id = next_client_id.fetch_add(1, Ordering::Relaxed);
+                    // an unreadable key is treated like no key, which signs up a new player
+                    let key = authorization
+                        .as_deref()
+                        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+                        .and_then(decode_key);
+                    match key.map(|key| is_banned(&persistence, &key)) {
+                        Some(Ok(true)) => {
+                            info!("Refused a connect token to a banned player");
+                            return warp::reply::with_status(String::new(), StatusCode::FORBIDDEN);
+                        }
+                        Some(Err(error)) => error!("Failed to look up a player: {error}"),
+                        _ => {}
+                    }
+                    match issue_token(client_id, key, public_addr, &private_key, &cert_hash) {
+                        Ok(token) => {
+                            info!("Issued a connect token for client {client_id}");
+                            warp::reply::with_status(token, StatusCode::OK)
+                        }
+                        Err(error) => {
+                            error!("Failed to issue a connect token: {error}");
+                            warp::reply::with_status(
+                                String::new(),
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                            )
+                        }
+                    }
+                });
+            // the admin API is not meant for browsers, so it is left out of CORS
+            serve(tokens_listener, serve_tokens.or(api).with(cors).or(admin)).await;
+        });
+        runtime.spawn(serve(
+            metrics_listener,
+            metrics::routes(self.metrics.clone()),
         ));
-    }
-}
 
-struct NativeServerTransportPlugin {
-    server_address: WebServerDestination,
-    tokens_address: WebServerDestination,
+        app.insert_resource(TokioRuntime(runtime));
+        app.insert_resource(transport);
+    }
 }
 
-impl NativeServerTransportPlugin {
-    fn _url(host: &str, port: &str, tokens_port: &str) -> Self {
-        Self {
-            server_address: WebServerDestination::Url(format!("{host}:{port}").parse().unwrap()),
-            tokens_address: WebServerDestination::Url(
-                format!("{host}:{}", tokens_port).parse().unwrap(),
-            ),
-        }
+async fn serve<F>(listener: TcpListener, routes: F)
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    match tokio::net::TcpListener::from_std(listener) {
+        Ok(listener) => warp::serve(routes).run_incoming(incoming(listener)).await,
+        Err(error) => error!("Failed to serve HTTP: {error}"),
     }
+}
 
-    fn ip(ip: &str, port: &str, tokens_port: &str) -> Self {
-        Self {
-            server_address: WebServerDestination::Addr(format!("{ip}:{port}").parse().unwrap()),
-            tokens_address: WebServerDestination::Addr(
-                format!("{ip}:{}", tokens_port).parse().unwrap(),
-            ),
+// The connections made to `listener`. Failing to accept one (e.g. when out of file
+// descriptors) only drops that connection rather than the whole server.
+fn incoming(
+    listener: tokio::net::TcpListener,
+) -> impl Stream<Item = Result<tokio::net::TcpStream, std::io::Error>> {
+    futures_util::stream::unfold(listener, |listener| async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => return Some((Ok(stream), listener)),
+                Err(error) => error!("Failed to accept a connection: {error}"),
+            }
         }
-    }
+    })
 }
 
-impl Default for NativeServerTransportPlugin {
-    fn default() -> Self {
-        Self::ip("0.0.0.0", "7636", "7637")
+fn is_banned(persistence: &Persistence, key: &AccountKey) -> Result<bool, StorageError> {
+    let storage = persistence.storage();
+    match find_player(&*storage, key)? {
+        Some(player) => storage.is_banned(player.id),
+        None => Ok(false),
     }
 }
 
-impl Plugin for NativeServerTransportPlugin {
-    fn build(&self, app: &mut App) {
-        use bevy_renet2::renet2::transport::{
-            NetcodeServerTransport, ServerAuthentication, ServerSetupConfig,
-        };
-        use std::time::SystemTime;
-
-        let public_addr: SocketAddr = self.server_address.clone().into();
+// Signs a connect token for `client_id` (carrying their account `key`, if any) and bundles it
+// with the certificate hash, encoded the way `client::ClientPlugin` expects its `server_token`.
+fn issue_token(
+    client_id: u64,
+    key: Option<AccountKey>,
+    public_addr: SocketAddr,
+    private_key: &[u8; NETCODE_KEY_BYTES],
+    cert_hash: &[u8],
+) -> Result<String, TokenError> {
+    use base64::Engine;
 
-        let current_time: std::time::Duration = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap();
-        let server_config = ServerSetupConfig {
-            current_time,
-            max_clients: 64,
-            protocol_id: PROTOCOL_ID,
-            socket_addresses: vec![vec![public_addr]],
-            authentication: ServerAuthentication::Unsecure,
-        };
-
-        let socket = {
-            use base64::Engine;
-
-            #[derive(Resource)]
-            pub struct TokioRuntime(#[allow(dead_code)] tokio::runtime::Runtime);
-
-            println!("Opening WT Socket on {}", public_addr);
-
-            let (config, cert_hash) =
-                renet2::transport::WebTransportServerConfig::new_selfsigned(public_addr, 4);
-
-            let cert_hash_b64 =
-                base64::engine::general_purpose::STANDARD.encode(cert_hash.hash.as_ref());
-            let certs_socket: SocketAddr = self.tokens_address.clone().into();
-
-            let runtime = tokio::runtime::Runtime::new().unwrap();
-            runtime.spawn(async move {
-                let cors = warp::cors()
-                    .allow_method("GET")
-                    .allow_origin("http://localhost:8000")
-                    .allow_origin("http://localhost:8080")
-                    .allow_origin("http://127.0.0.1:8000")
-                    .allow_origin("http://127.0.0.1:8080")
-                    .allow_origin("https://wordfight.deno.dev");
-                let serve_certs = warp::path::end()
-                    .map(move || cert_hash_b64.clone())
-                    .with(cors);
-                warp::serve(serve_certs).run(certs_socket).await;
-            });
-
-            let socket =
-                renet2::transport::WebTransportServer::new(config, runtime.handle().clone())
-                    .unwrap();
-            app.insert_resource(TokioRuntime(runtime));
-            socket
-        };
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO);
+    let connect_token = ConnectToken::generate(
+        current_time,
+        PROTOCOL_ID,
+        TOKEN_EXPIRE_SECS,
+        client_id,
+        TOKEN_TIMEOUT_SECS,
+        vec![public_addr],
+        Some(&key_to_user_data(key)),
+        private_key,
+    )?;
+    let mut connect_token_bytes = Vec::new();
+    connect_token.write(&mut connect_token_bytes)?;
+    let token = ServerToken {
+        cert_hash: cert_hash.to_vec(),
+        connect_token: connect_token_bytes,
+    };
+    Ok(base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&token)?))
+}
 
-        let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
-        app.insert_resource(transport);
-    }
+#[derive(Debug, Error)]
+enum TokenError {
+    #[error("Failed to generate connect token: {0}")]
+    Generate(#[from] TokenGenerationError),
+    #[error("Failed to write connect token: {0}")]
+    Write(#[from] std::io::Error),
+    #[error("Failed to encode server token: {0}")]
+    Encode(#[from] bincode::Error),
 }
@@ -33,8 +33,15 @@ impl Plugin for StartupPlugin {
 #[cfg(feature = "dev")]
 impl StartupPlugin {
     fn spawn_game(mut commands: Commands) {
+        // the local player, whose input the server receives as its own
         let client1 = commands.spawn(Client::from(ClientId::SERVER).bundle()).id();
-        let client2 = commands.spawn(Client::from(ClientId::SERVER).bundle()).id();
+        // every client needs an id of its own, or its actions would go to the other player
+        #[cfg(not(feature = "bots"))]
+        let client2 = commands.spawn(Client::from(ClientId::new(1)).bundle()).id();
+        #[cfg(feature = "bots")]
+        let client2 = commands
+            .spawn(bot_controller::BotController::default().bundle())
+            .id();
         commands.trigger(SpawnGame::new(7, client1, client2));
     }
 }